@@ -0,0 +1,262 @@
+use serde::{Deserialize, Serialize};
+use wgpu_bootstrap::{
+    wgpu,
+    winit::{
+        event::{ElementState, WindowEvent},
+        keyboard::{KeyCode, PhysicalKey},
+    },
+};
+
+use crate::{
+    data_containers::Sphere,
+    gpu::{ComputeStage, GpuContext},
+    scene::SphereConfig,
+    vector::{add, scale, sub},
+};
+
+// Keys moving the keyboard driven spheres, with their direction
+const MOVE_KEYS: [(KeyCode, [f32; 3]); 6] = [
+    (KeyCode::ArrowLeft, [-1.0, 0.0, 0.0]),
+    (KeyCode::ArrowRight, [1.0, 0.0, 0.0]),
+    (KeyCode::ArrowUp, [0.0, 0.0, -1.0]),
+    (KeyCode::ArrowDown, [0.0, 0.0, 1.0]),
+    (KeyCode::PageUp, [0.0, 1.0, 0.0]),
+    (KeyCode::PageDown, [0.0, -1.0, 0.0]),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Keyframe {
+    pub time: f32, // Seconds
+    pub offset: [f32; 3],
+}
+
+/// Kinematic motion of a sphere collider, as an offset from its `center`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum SphereAnimation {
+    // Circle of `radius` around the vertical axis through the center, one turn per `period` seconds
+    Orbit { radius: f32, period: f32 },
+    // Bounces up to `height` above the center, one per `period` seconds
+    Bounce { height: f32, period: f32 },
+    // Linear interpolation between the keyframes, sorted by time, played again
+    // from the start after the last one if `looping`
+    Keyframes {
+        keyframes: Vec<Keyframe>,
+        #[serde(default)]
+        looping: bool,
+    },
+    // Arrow keys in the horizontal plane and Page Up / Page Down vertically, at `speed` m/s
+    Keyboard { speed: f32 },
+}
+
+impl SphereAnimation {
+    // Offset at `time` of the animations following a path
+    fn path_offset(&self, time: f32) -> [f32; 3] {
+        match self {
+            SphereAnimation::Orbit { radius, period } => {
+                let (sin, cos) = (std::f32::consts::TAU * time / period).sin_cos();
+                [radius * cos, 0.0, radius * sin]
+            }
+            SphereAnimation::Bounce { height, period } => {
+                [0.0, height * (std::f32::consts::PI * time / period).sin().abs(), 0.0]
+            }
+            SphereAnimation::Keyframes { keyframes, looping } => keyframe_offset(keyframes, *looping, time),
+            SphereAnimation::Keyboard { .. } => [0.0; 3],
+        }
+    }
+}
+
+fn keyframe_offset(keyframes: &[Keyframe], looping: bool, time: f32) -> [f32; 3] {
+    let (Some(first), Some(last)) = (keyframes.first(), keyframes.last()) else {
+        return [0.0; 3];
+    };
+
+    let duration = last.time - first.time;
+    let time = if looping && duration > 0.0 {
+        first.time + (time - first.time).rem_euclid(duration)
+    } else {
+        time
+    };
+
+    let next = keyframes.partition_point(|keyframe| keyframe.time <= time);
+    if next == 0 {
+        return first.offset;
+    }
+    if next == keyframes.len() {
+        return last.offset;
+    }
+
+    let (a, b) = (&keyframes[next - 1], &keyframes[next]);
+    let t = (time - a.time) / (b.time - a.time);
+    add(a.offset, scale(sub(b.offset, a.offset), t))
+}
+
+/// Positions and velocities of the animated spheres of a scene, frame by
+/// frame. The keyboard driven spheres keep the offset they were moved to.
+#[derive(Clone, Debug)]
+pub struct SphereAnimator {
+    keyboard_offsets: Vec<[f32; 3]>,
+    // Whether a sphere is driven by the keyboard
+    keyboard: bool,
+    pressed: [bool; MOVE_KEYS.len()],
+}
+
+impl SphereAnimator {
+    pub fn new(spheres: &[SphereConfig]) -> Self {
+        Self {
+            keyboard_offsets: vec![[0.0; 3]; spheres.len()],
+            keyboard: spheres
+                .iter()
+                .any(|sphere| matches!(sphere.animation, Some(SphereAnimation::Keyboard { .. }))),
+            pressed: [false; MOVE_KEYS.len()],
+        }
+    }
+
+    pub fn is_animated(spheres: &[SphereConfig]) -> bool {
+        spheres.iter().any(|sphere| sphere.animation.is_some())
+    }
+
+    /// Updates the pressed keys from a window event, returns whether it was used.
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        let WindowEvent::KeyboardInput { event, .. } = event else {
+            return false;
+        };
+        if !self.keyboard {
+            return false;
+        }
+        let PhysicalKey::Code(code) = event.physical_key else {
+            return false;
+        };
+
+        match MOVE_KEYS.iter().position(|&(key, _)| key == code) {
+            Some(index) => {
+                self.pressed[index] = event.state == ElementState::Pressed;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.keyboard_offsets.iter_mut().for_each(|offset| *offset = [0.0; 3]);
+    }
+
+    /// Spheres at `time`, with the velocity bringing them to their position
+    /// at `time + delta_time`.
+    pub fn spheres(&mut self, configs: &[SphereConfig], time: f32, delta_time: f32) -> Vec<Sphere> {
+        let direction = MOVE_KEYS
+            .iter()
+            .zip(self.pressed)
+            .filter(|&(_, pressed)| pressed)
+            .fold([0.0; 3], |direction, (&(_, key_direction), _)| add(direction, key_direction));
+
+        configs
+            .iter()
+            .zip(&mut self.keyboard_offsets)
+            .map(|(config, keyboard_offset)| {
+                let (start, end) = match &config.animation {
+                    None => ([0.0; 3], [0.0; 3]),
+                    Some(SphereAnimation::Keyboard { speed }) => {
+                        let start = *keyboard_offset;
+                        *keyboard_offset = add(start, scale(direction, speed * delta_time));
+                        (start, *keyboard_offset)
+                    }
+                    Some(animation) => (animation.path_offset(time), animation.path_offset(time + delta_time)),
+                };
+                let velocity = if delta_time > 0.0 {
+                    scale(sub(end, start), 1.0 / delta_time)
+                } else {
+                    [0.0; 3]
+                };
+                let [x, y, z] = add(config.center, start);
+
+                Sphere {
+                    x,
+                    y,
+                    z,
+                    radius: config.radius,
+                    friction_factor: config.friction_factor,
+                    velocity_x: velocity[0],
+                    velocity_y: velocity[1],
+                    velocity_z: velocity[2],
+                }
+            })
+            .collect()
+    }
+}
+
+/// GPU stage moving the spheres at their velocity before every substep, so
+/// the colliders don't jump once per frame.
+pub struct SphereMotion {
+    stage: ComputeStage,
+}
+
+impl SphereMotion {
+    pub fn new(context: &impl GpuContext, sphere_buffer: &wgpu::Buffer, compute_data_buffer: &wgpu::Buffer,
+        nb_spheres: u32) -> Self {
+        let source = concat!(include_str!("shaders/colliders.wgsl"), include_str!("shaders/sphere_motion.wgsl"));
+
+        Self {
+            stage: ComputeStage::new(context, "Sphere Motion", source, "main",
+                [&[(0, sphere_buffer)], &[(0, compute_data_buffer)]], nb_spheres),
+        }
+    }
+
+    pub fn dispatch<'a>(&'a self, compute_pass: &mut wgpu::ComputePass<'a>) {
+        self.stage.dispatch(compute_pass);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyframes() -> Vec<Keyframe> {
+        vec![
+            Keyframe { time: 1.0, offset: [0.0, 0.0, 0.0] },
+            Keyframe { time: 2.0, offset: [2.0, 0.0, 0.0] },
+            Keyframe { time: 3.0, offset: [2.0, 4.0, 0.0] },
+        ]
+    }
+
+    #[test]
+    fn keyframes_hold_before_the_first_and_after_the_last() {
+        assert_eq!(keyframe_offset(&keyframes(), false, 0.0), [0.0, 0.0, 0.0]);
+        assert_eq!(keyframe_offset(&keyframes(), false, 5.0), [2.0, 4.0, 0.0]);
+        assert_eq!(keyframe_offset(&[], false, 1.0), [0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn keyframes_are_interpolated() {
+        assert_eq!(keyframe_offset(&keyframes(), false, 1.5), [1.0, 0.0, 0.0]);
+        assert_eq!(keyframe_offset(&keyframes(), false, 2.0), [2.0, 0.0, 0.0]);
+        assert_eq!(keyframe_offset(&keyframes(), false, 2.25), [2.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn looping_keyframes_wrap_around() {
+        assert_eq!(keyframe_offset(&keyframes(), true, 3.5), keyframe_offset(&keyframes(), false, 1.5));
+        assert_eq!(keyframe_offset(&keyframes(), true, 4.25), keyframe_offset(&keyframes(), false, 2.25));
+        // Before the first keyframe too
+        assert_eq!(keyframe_offset(&keyframes(), true, 0.5), keyframe_offset(&keyframes(), false, 2.5));
+    }
+
+    #[test]
+    fn sphere_velocity_reaches_the_next_position() {
+        let configs = [SphereConfig {
+            center: [0.0, 1.0, 0.0],
+            animation: Some(SphereAnimation::Keyframes { keyframes: keyframes(), looping: false }),
+            ..SphereConfig::default()
+        }];
+        let mut animator = SphereAnimator::new(&configs);
+
+        let sphere = animator.spheres(&configs, 1.5, 0.25)[0];
+        assert_eq!(sphere.center(), [1.0, 1.0, 0.0]);
+        // From [1, 0, 0] to [1.5, 0, 0] in 0.25 s
+        assert_eq!(sphere.velocity(), [2.0, 0.0, 0.0]);
+
+        let sphere = animator.spheres(&configs, 1.5, 0.0)[0];
+        assert_eq!(sphere.velocity(), [0.0; 3]);
+    }
+}
@@ -0,0 +1,217 @@
+use wgpu_bootstrap::{
+    cgmath::{self, InnerSpace, Matrix4, Point3, Vector3},
+    winit::{
+        dpi::PhysicalPosition,
+        event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent},
+        keyboard::{KeyCode, PhysicalKey},
+    },
+};
+
+#[rustfmt::skip]
+const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 0.5, 0.0,
+    0.0, 0.0, 0.5, 1.0,
+);
+
+/// Content of the camera uniform buffer, same layout as `CameraUniform` in
+/// the render shaders.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CameraUniform {
+    pub view: [[f32; 4]; 4],
+    pub proj: [[f32; 4]; 4],
+}
+
+impl CameraUniform {
+    pub fn new(eye: Point3<f32>, target: Point3<f32>, up: Vector3<f32>, aspect: f32, fovy: f32,
+        znear: f32, zfar: f32) -> Self {
+        let view = Matrix4::look_at_rh(eye, target, up);
+        let proj = OPENGL_TO_WGPU_MATRIX * cgmath::perspective(cgmath::Deg(fovy), aspect, znear, zfar);
+
+        Self {
+            view: view.into(),
+            proj: proj.into(),
+        }
+    }
+}
+
+// Mouse and scroll sensitivity of the orbit camera
+const ORBIT_SPEED: f32 = 0.005; // Radians per pixel
+const PAN_SPEED: f32 = 0.0015; // Fraction of the distance per pixel
+const ZOOM_FACTOR: f32 = 1.1; // Per scroll line
+const SCROLL_LINE_PIXELS: f32 = 40.0;
+const MIN_DISTANCE: f32 = 0.1;
+const MAX_DISTANCE: f32 = 50.0;
+// Keeps the view direction away from the up axis, where `look_at` degenerates
+const PITCH_LIMIT: f32 = 89.0 * std::f32::consts::PI / 180.0;
+
+/// Half line from the eye through a point of the screen.
+#[derive(Copy, Clone, Debug)]
+pub struct Ray {
+    pub origin: Point3<f32>,
+    pub direction: Vector3<f32>,
+}
+
+impl Ray {
+    pub fn at(&self, distance: f32) -> Point3<f32> {
+        self.origin + self.direction * distance
+    }
+}
+
+/// Preset point of views around the target.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum View {
+    Front,
+    Side,
+    Top,
+}
+
+/// Camera turning around a target: the right button orbits, the middle button
+/// pans, the wheel zooms and the keys 1, 2 and 3 switch to the front, side and
+/// top views. The left button is left free to grab the clothe.
+#[derive(Clone, Debug)]
+pub struct OrbitCamera {
+    pub target: Point3<f32>,
+    pub distance: f32,
+    // Angle around the vertical axis, 0 is looking along -x
+    pub yaw: f32,
+    // Angle above the horizontal plane
+    pub pitch: f32,
+    cursor: Option<PhysicalPosition<f64>>,
+    orbiting: bool,
+    panning: bool,
+}
+
+impl OrbitCamera {
+    pub fn new(eye: Point3<f32>, target: Point3<f32>) -> Self {
+        let offset = eye - target;
+        let distance = offset.magnitude().max(MIN_DISTANCE);
+
+        Self {
+            target,
+            distance,
+            yaw: offset.z.atan2(offset.x),
+            pitch: (offset.y / distance).clamp(-1.0, 1.0).asin().clamp(-PITCH_LIMIT, PITCH_LIMIT),
+            cursor: None,
+            orbiting: false,
+            panning: false,
+        }
+    }
+
+    pub fn eye(&self) -> Point3<f32> {
+        self.target + self.distance * self.direction()
+    }
+
+    // Unit vector from the target to the eye
+    fn direction(&self) -> Vector3<f32> {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+
+        Vector3::new(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw)
+    }
+
+    pub fn orbit(&mut self, dx: f32, dy: f32) {
+        self.yaw = (self.yaw + dx * ORBIT_SPEED) % std::f32::consts::TAU;
+        self.pitch = (self.pitch + dy * ORBIT_SPEED).clamp(-PITCH_LIMIT, PITCH_LIMIT);
+    }
+
+    // Forward, right and up axes of the view
+    fn basis(&self) -> [Vector3<f32>; 3] {
+        let forward = -self.direction();
+        let right = forward.cross(Vector3::unit_y()).normalize();
+
+        [forward, right, right.cross(forward)]
+    }
+
+    pub fn cursor(&self) -> Option<PhysicalPosition<f64>> {
+        self.cursor
+    }
+
+    /// Ray through the pixel `position` of a `width` x `height` window, for a
+    /// vertical field of view of `fovy` degrees.
+    pub fn ray(&self, position: PhysicalPosition<f64>, width: u32, height: u32, fovy: f32) -> Ray {
+        let [forward, right, up] = self.basis();
+        let tan = (fovy.to_radians() / 2.0).tan();
+        let aspect = width as f32 / height.max(1) as f32;
+        let x = 2.0 * position.x as f32 / width.max(1) as f32 - 1.0;
+        let y = 1.0 - 2.0 * position.y as f32 / height.max(1) as f32;
+
+        Ray {
+            origin: self.eye(),
+            direction: (forward + right * (x * tan * aspect) + up * (y * tan)).normalize(),
+        }
+    }
+
+    // Moves the target in the view plane, so the scene follows the cursor
+    pub fn pan(&mut self, dx: f32, dy: f32) {
+        let [_, right, up] = self.basis();
+        let scale = self.distance * PAN_SPEED;
+
+        self.target += (up * dy - right * dx) * scale;
+    }
+
+    pub fn zoom(&mut self, lines: f32) {
+        self.distance = (self.distance * ZOOM_FACTOR.powf(-lines)).clamp(MIN_DISTANCE, MAX_DISTANCE);
+    }
+
+    pub fn set_view(&mut self, view: View) {
+        (self.yaw, self.pitch) = match view {
+            View::Front => (0.0, 0.0),
+            View::Side => (std::f32::consts::FRAC_PI_2, 0.0),
+            View::Top => (0.0, PITCH_LIMIT),
+        };
+    }
+
+    /// Updates the camera from a window event, returns whether it was used.
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::MouseInput { state, button, .. } => {
+                let pressed = *state == ElementState::Pressed;
+                match button {
+                    MouseButton::Right => self.orbiting = pressed,
+                    MouseButton::Middle => self.panning = pressed,
+                    _ => return false,
+                }
+                true
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                if let Some(previous) = self.cursor.replace(*position) {
+                    let dx = (position.x - previous.x) as f32;
+                    let dy = (position.y - previous.y) as f32;
+
+                    if self.orbiting {
+                        self.orbit(dx, dy);
+                    } else if self.panning {
+                        self.pan(dx, dy);
+                    }
+                }
+                self.orbiting || self.panning
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.cursor = None;
+                false
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / SCROLL_LINE_PIXELS,
+                };
+                self.zoom(lines);
+                true
+            }
+            WindowEvent::KeyboardInput { event, .. } if event.state == ElementState::Pressed => {
+                let view = match event.physical_key {
+                    PhysicalKey::Code(KeyCode::Digit1) => View::Front,
+                    PhysicalKey::Code(KeyCode::Digit2) => View::Side,
+                    PhysicalKey::Code(KeyCode::Digit3) => View::Top,
+                    _ => return false,
+                };
+                self.set_view(view);
+                true
+            }
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use std::path::PathBuf;
+
+use crate::export::MeshFormat;
+
+/// Command line arguments of the simulator.
+#[derive(Debug)]
+pub struct Args {
+    pub scene: Option<PathBuf>,
+    pub headless: bool,
+    pub frames: u32,
+    pub output: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    pub fps: f32,
+    pub turntable: bool,
+    pub software: bool,
+    pub export: Option<PathBuf>,
+    pub export_format: MeshFormat,
+    pub export_every: u32,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            scene: None,
+            headless: false,
+            frames: 100,
+            output: PathBuf::from("frames"),
+            width: 1280,
+            height: 720,
+            fps: 60.0,
+            turntable: false,
+            software: false,
+            export: None,
+            export_format: MeshFormat::Obj,
+            export_every: 1,
+        }
+    }
+}
+
+fn next_value<I: Iterator<Item = String>>(args: &mut I, name: &str) -> Result<String, String> {
+    args.next().ok_or(format!("`{}` expects a value", name))
+}
+
+fn parse_value<T: std::str::FromStr>(value: String, name: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid value `{}` for `{}`", value, name))
+}
+
+impl Args {
+    pub const USAGE: &'static str = "usage: clothe-simulator [--scene <path>] \
+        [--headless [--frames <count>] [--output <dir>] [--width <px>] [--height <px>] [--fps <fps>] [--turntable] [--software]] \
+        [--export <dir> [--export-format obj|ply] [--export-every <frames>]]";
+
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Self, String> {
+        let mut parsed = Args::default();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--scene" => parsed.scene = Some(PathBuf::from(next_value(&mut args, &arg)?)),
+                "--headless" => parsed.headless = true,
+                "--frames" => parsed.frames = parse_value(next_value(&mut args, &arg)?, &arg)?,
+                "--output" => parsed.output = PathBuf::from(next_value(&mut args, &arg)?),
+                "--width" => parsed.width = parse_value(next_value(&mut args, &arg)?, &arg)?,
+                "--height" => parsed.height = parse_value(next_value(&mut args, &arg)?, &arg)?,
+                "--fps" => parsed.fps = parse_value(next_value(&mut args, &arg)?, &arg)?,
+                "--turntable" => parsed.turntable = true,
+                "--software" => parsed.software = true,
+                "--export" => parsed.export = Some(PathBuf::from(next_value(&mut args, &arg)?)),
+                "--export-format" => parsed.export_format = next_value(&mut args, &arg)?.parse()?,
+                "--export-every" => parsed.export_every = parse_value(next_value(&mut args, &arg)?, &arg)?,
+                _ => return Err(format!("unknown argument `{}`", arg)),
+            }
+        }
+
+        if parsed.frames == 0 {
+            return Err("`--frames` must be at least 1".to_string());
+        }
+        if parsed.export_every == 0 {
+            return Err("`--export-every` must be at least 1".to_string());
+        }
+        if parsed.width == 0 || parsed.height == 0 {
+            return Err("`--width` and `--height` must be greater than 0".to_string());
+        }
+        if !parsed.fps.is_finite() || parsed.fps <= 0.0 {
+            return Err("`--fps` must be greater than 0".to_string());
+        }
+
+        Ok(parsed)
+    }
+}
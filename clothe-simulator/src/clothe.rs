@@ -1,8 +1,73 @@
+use std::{collections::HashMap, fmt, mem::size_of};
+
+use serde::{Deserialize, Serialize};
+use wgpu_bootstrap::wgpu;
+
 use crate::{
+    export::unique_triangles,
+    import::{ImportError, ObjMesh},
+    integrator::IntegratorState,
     node::Node,
-    spring::Spring,
+    spring::{Spring, SpringDirection, SpringKind},
+    vector::{cross, length, sub},
 };
 
+// Vertices that stay fixed during the simulation. The top of the clothe is
+// the line of vertices with the lowest z, its corners are the ends of this line.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PinSpec {
+    #[default]
+    None,
+    TopCorners,
+    TopEdge,
+    Indices(Vec<u32>),
+}
+
+#[derive(Debug)]
+pub enum ClotheError {
+    NoSquare,
+    TooManySquares(u32),
+    Import(ImportError),
+    TooManyTriangles(usize),
+    InvalidPin(u32),
+    BufferTooLarge { name: &'static str, size: u64, limit: u64 },
+}
+
+impl fmt::Display for ClotheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClotheError::NoSquare => write!(f, "the clothe needs at least one square"),
+            ClotheError::TooManySquares(number_square) => write!(
+                f,
+                "a clothe of {0}x{0} squares can't be indexed with 32 bits indices, the maximum is {1}x{1}",
+                number_square,
+                Clothe::MAX_SQUARES,
+            ),
+            ClotheError::Import(error) => write!(f, "{}", error),
+            ClotheError::TooManyTriangles(nb_triangles) => write!(
+                f,
+                "a mesh of {} triangles can't be indexed with 32 bits indices",
+                nb_triangles,
+            ),
+            ClotheError::InvalidPin(index) => write!(f, "the pinned vertex {} doesn't exist", index),
+            ClotheError::BufferTooLarge { name, size, limit } => write!(
+                f,
+                "the {} buffer of the clothe takes {} bytes, the device accepts at most {} bytes",
+                name, size, limit,
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ClotheError {}
+
+impl From<ImportError> for ClotheError {
+    fn from(error: ImportError) -> Self {
+        ClotheError::Import(error)
+    }
+}
+
 pub struct Clothe {
     length: f32,
     number_square: u32,
@@ -11,12 +76,78 @@ pub struct Clothe {
     pub center_z: f32,
     pub nb_vertices: u32,
     pub vertices: Vec<Node>,
-    pub indices: Vec<u16>,
+    pub indices: Vec<u32>,
+    pub spring_offsets: Vec<u32>,
     pub springs: Vec<Spring>,
+    pub masses: Vec<f32>,
 }
 
 impl Clothe {
-    pub fn new(length: f32, number_square: u32, center: &[f32; 3]) -> Self {
+    // Each square is drawn with 4 triangles, 12 indices that must fit in the
+    // u32 range of the draw call
+    pub const MAX_SQUARES: u32 = 18918;
+    // Two triangles sharing an edge belong to the same quad when the angles
+    // facing this edge are both about 90 degrees
+    const QUAD_ANGLE_COSINE: f32 = 0.2;
+
+    pub fn check_size(number_square: u32) -> Result<(), ClotheError> {
+        match number_square {
+            0 => Err(ClotheError::NoSquare),
+            n if n > Self::MAX_SQUARES => Err(ClotheError::TooManySquares(n)),
+            _ => Ok(()),
+        }
+    }
+
+    // Size of every storage buffer bound for the clothe, checked before the
+    // clothe is built. The solver states are the largest per vertex buffers,
+    // the XPBD ones are smaller than `IntegratorState`. The springs overflow first.
+    fn check_buffers(nb_vertices: u64, nb_springs: u64, nb_triangles: u64, limits: &wgpu::Limits)
+        -> Result<(), ClotheError> {
+        let limit = (limits.max_storage_buffer_binding_size as u64).min(limits.max_buffer_size);
+        let buffers = [
+            ("vertex", nb_vertices * size_of::<Node>() as u64),
+            ("solver state", nb_vertices * size_of::<IntegratorState>() as u64),
+            ("mass", nb_vertices * size_of::<f32>() as u64),
+            // Same size as the offsets of the triangles around each vertex
+            ("spring offset", (nb_vertices + 1) * size_of::<u32>() as u64),
+            ("spring", nb_springs * size_of::<Spring>() as u64),
+            // Each triangle is drawn twice, one per side
+            ("index", nb_triangles * 6 * size_of::<u32>() as u64),
+            // Same size as the triangles around each vertex
+            ("triangle", nb_triangles * size_of::<[u32; 3]>() as u64),
+        ];
+
+        for (name, size) in buffers {
+            if size > limit {
+                return Err(ClotheError::BufferTooLarge { name, size, limit });
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that the buffers of a grid clothe fit in the device limits.
+    pub fn check_grid_buffers(number_square: u32, limits: &wgpu::Limits) -> Result<(), ClotheError> {
+        Self::check_size(number_square)?;
+
+        // Per side, the vertices have structural and bend links, the squares
+        // two shear links. Every link is stored at both ends.
+        let (n, m) = (number_square as u64, number_square as u64 + 1);
+        let nb_links = 2 * n * m + 2 * n * n + 2 * m * m.saturating_sub(2);
+        Self::check_buffers(m * m, 2 * nb_links, 2 * n * n, limits)
+    }
+
+    /// Check that the buffers of a mesh clothe fit in the device limits.
+    pub fn check_mesh_buffers(mesh: &ObjMesh, limits: &wgpu::Limits) -> Result<(), ClotheError> {
+        // Every edge is a link and every edge shared by two triangles adds at
+        // most one bend link, see `construct_mesh_springs`: at most one link
+        // per triangle side
+        let nb_triangles = mesh.triangles.len() as u64;
+        Self::check_buffers(mesh.positions.len() as u64, 2 * 3 * nb_triangles, nb_triangles, limits)
+    }
+
+    pub fn new(length: f32, number_square: u32, center: &[f32; 3], pins: &PinSpec) -> Result<Self, ClotheError> {
+        Self::check_size(number_square)?;
+
         let mut instance = Self {
             length,
             number_square,
@@ -25,23 +156,203 @@ impl Clothe {
             center_x: center[0],
             center_y: center[1],
             center_z: center[2],
+            spring_offsets: Vec::new(),
             springs: Vec::new(),
+            masses: Vec::new(),
             nb_vertices: 0,
         };
 
         instance.construct_vertices();
-        instance
+        instance.set_mass(1.0);
+        instance.pin_vertices(pins)?;
+        Ok(instance)
+    }
+
+    /// Clothe made of the triangles of a mesh, moved by `center`. The edges
+    /// are structural links, or shear links when they are the diagonal of a
+    /// quad, and the vertices facing each other across an edge get a bend link.
+    pub fn from_mesh(mesh: &ObjMesh, center: &[f32; 3], pins: &PinSpec) -> Result<Self, ClotheError> {
+        // Each triangle is drawn twice, one per side
+        if mesh.triangles.len() > u32::MAX as usize / 6 {
+            return Err(ClotheError::TooManyTriangles(mesh.triangles.len()));
+        }
+
+        let mut instance = Self {
+            length: 0.0,
+            number_square: 0,
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            center_x: center[0],
+            center_y: center[1],
+            center_z: center[2],
+            spring_offsets: Vec::new(),
+            springs: Vec::new(),
+            masses: Vec::new(),
+            nb_vertices: 0,
+        };
+
+        mesh.positions.iter().zip(&mesh.tex_coords).for_each(|(position, tex_coords)| {
+            let _ = instance.insert_vertex(position[0] + center[0], position[1] + center[1],
+                position[2] + center[2], tex_coords[0], tex_coords[1]);
+        });
+        instance.nb_vertices = instance.vertices.len() as u32;
+
+        let triangles: Vec<[u32; 3]> = mesh
+            .triangles
+            .iter()
+            .copied()
+            .filter(|&[a, b, c]| a != b && b != c && c != a)
+            .collect();
+        triangles.iter().for_each(|&[a, b, c]| instance.indices.extend_from_slice(&[a, b, c]));
+        triangles.iter().for_each(|&[a, b, c]| instance.indices.extend_from_slice(&[a, c, b]));
+
+        instance.construct_mesh_springs(&triangles);
+        instance.set_mass(1.0);
+        instance.pin_vertices(pins)?;
+        Ok(instance)
+    }
+
+    /// Same mass for every vertex, in kg.
+    pub fn set_mass(&mut self, mass: f32) {
+        self.masses = vec![mass; self.nb_vertices as usize];
+    }
+
+    /// Mass of every vertex from the areal density of the fabric in g/m², each
+    /// triangle gives a third of its weight to each of its corners. The total
+    /// weight doesn't depend on the resolution.
+    pub fn set_areal_density(&mut self, density: f32) {
+        let mut masses = vec![0.0; self.nb_vertices as usize];
+
+        for triangle in unique_triangles(&self.indices) {
+            let [a, b, c] = triangle.map(|i| {
+                let position = self.vertices[i as usize].position;
+                [position[0], position[1], position[2]]
+            });
+            let area = 0.5 * length(cross(sub(b, a), sub(c, a)));
+
+            for i in triangle {
+                masses[i as usize] += density * 1e-3 * area / 3.0;
+            }
+        }
+
+        // Vertices outside of every triangle keep a tiny mass, the solvers divide by it
+        self.masses = masses.into_iter().map(|mass: f32| mass.max(f32::MIN_POSITIVE)).collect();
     }
 
-    fn insert_vertex(&mut self, x: f32, y: f32, z: f32, x_tex_coords: f32, y_tex_coords: f32) -> u16 {
+    fn pin_vertices(&mut self, pins: &PinSpec) -> Result<(), ClotheError> {
+        let top = self.top_edge();
+        let indices: Vec<u32> = match pins {
+            PinSpec::None => Vec::new(),
+            PinSpec::TopCorners => {
+                let x = |&i: &u32| self.vertices[i as usize].position[0];
+                let left = top.iter().min_by(|a, b| x(a).total_cmp(&x(b)));
+                let right = top.iter().max_by(|a, b| x(a).total_cmp(&x(b)));
+                left.into_iter().chain(right).copied().collect()
+            }
+            PinSpec::TopEdge => top,
+            PinSpec::Indices(indices) => indices.clone(),
+        };
+
+        for i in indices {
+            let vertex = self.vertices.get_mut(i as usize).ok_or(ClotheError::InvalidPin(i))?;
+            vertex.pinned = 1;
+        }
+        Ok(())
+    }
+
+    // Vertices at the lowest z, the first row of the grid
+    fn top_edge(&self) -> Vec<u32> {
+        let z = |vertex: &Node| vertex.position[2];
+        let min_z = self.vertices.iter().map(z).fold(f32::INFINITY, f32::min);
+        let max_z = self.vertices.iter().map(z).fold(f32::NEG_INFINITY, f32::max);
+        let tolerance = 1e-6 * (max_z - min_z);
+
+        (0..self.nb_vertices)
+            .filter(|&i| z(&self.vertices[i as usize]) <= min_z + tolerance)
+            .collect()
+    }
+
+    // Cosine of the angle at `corner` in the triangle `corner`, `a`, `b`
+    fn corner_cosine(&self, corner: u32, a: u32, b: u32) -> f32 {
+        let position = |i: u32| self.vertices[i as usize].position;
+        let (p, pa, pb) = (position(corner), position(a), position(b));
+        let u = [pa[0] - p[0], pa[1] - p[1], pa[2] - p[2]];
+        let v = [pb[0] - p[0], pb[1] - p[1], pb[2] - p[2]];
+        let dot = u[0] * v[0] + u[1] * v[1] + u[2] * v[2];
+
+        dot / (self.get_norm_distance(corner, a) * self.get_norm_distance(corner, b)).max(f32::EPSILON)
+    }
+
+    fn construct_mesh_springs(&mut self, triangles: &[[u32; 3]]) {
+        // Vertices facing each edge
+        let mut edges: HashMap<(u32, u32), Vec<u32>> = HashMap::new();
+        for &[a, b, c] in triangles {
+            for (i, j, opposite) in [(a, b, c), (b, c, a), (c, a, b)] {
+                edges.entry((i.min(j), i.max(j))).or_default().push(opposite);
+            }
+        }
+
+        let mut links: HashMap<(u32, u32), SpringKind> = HashMap::new();
+        for (&(i, j), opposites) in &edges {
+            let is_diagonal = opposites.len() == 2
+                && opposites.iter().all(|&o| self.corner_cosine(o, i, j).abs() < Self::QUAD_ANGLE_COSINE);
+            let kind = if is_diagonal { SpringKind::Shear } else { SpringKind::Structural };
+            links.insert((i, j), kind);
+        }
+        for (&(i, j), opposites) in &edges {
+            if let [a, b] = opposites[..] {
+                // Across a diagonal the opposite vertices are the other diagonal of the quad
+                let kind = match links[&(i, j)] {
+                    SpringKind::Shear => SpringKind::Shear,
+                    _ => SpringKind::Bend,
+                };
+                if a != b {
+                    let link = links.entry((a.min(b), a.max(b))).or_insert(kind);
+                    *link = (*link).min(kind);
+                }
+            }
+        }
+
+        let mut neighbours = vec![Vec::new(); self.nb_vertices as usize];
+        for ((i, j), kind) in links {
+            neighbours[i as usize].push((j, kind));
+            neighbours[j as usize].push((i, kind));
+        }
+        // Same order on every run, whatever the hash map order
+        neighbours.iter_mut().for_each(|links| links.sort_unstable_by_key(|&(link, kind)| (kind, link)));
+
+        self.set_springs(&neighbours);
+    }
+
+    // Flat spring list from the linked vertices of every vertex
+    fn set_springs(&mut self, neighbours: &[Vec<(u32, SpringKind)>]) {
+        self.spring_offsets = vec![0];
+        self.springs = Vec::new();
+
+        for (index, links) in neighbours.iter().enumerate() {
+            for &(link, kind) in links {
+                let tex_coords = |i: usize| {
+                    let tex_coords = self.vertices[i].tex_coords;
+                    [tex_coords[0], tex_coords[1]]
+                };
+                let direction = SpringDirection::from_tex_coords(tex_coords(index), tex_coords(link as usize));
+
+                self.springs.push(Spring::new(link, self.get_norm_distance(index as u32, link), kind, direction));
+            }
+            self.spring_offsets.push(self.springs.len() as u32);
+        }
+    }
+
+    fn insert_vertex(&mut self, x: f32, y: f32, z: f32, x_tex_coords: f32, y_tex_coords: f32) -> u32 {
         self.vertices.push(Node {
             position: [x, y, z, 1.0],
             normal: [0.0, 0.0, 0.0, 1.0],
             velocity: [0.0, 0.0, 0.0, 1.0],
-            resultant: [0.0, 0.0, 0.0, 1.0],
+            resultant: [0.0, 0.0, 0.0],
+            pinned: 0,
             tex_coords: [x_tex_coords, y_tex_coords, 1.0, 1.0],
         });
-        self.vertices.len() as u16 - 1
+        self.vertices.len() as u32 - 1
     }
 
     fn get_norm_distance(&self, i: u32, j: u32) -> f32 {
@@ -80,11 +391,11 @@ impl Clothe {
         // Create indices
         (0..rows - 1).for_each(|row| {
             (0..cols - 1).for_each(|col| {
-                let indice = (rows * row + col) as u32;
-                let top_left = indice as u16;
-                let top_right = (indice as u16) + 1;
-                let bottom_left = (indice + rows) as u16;
-                let bottom_right = ((indice + rows) as u16) + 1;
+                let indice = rows * row + col;
+                let top_left = indice;
+                let top_right = indice + 1;
+                let bottom_left = indice + rows;
+                let bottom_right = indice + rows + 1;
 
                 self.indices.extend_from_slice(&[top_right, top_left, bottom_left]);
                 self.indices.extend_from_slice(&[top_right, bottom_left, bottom_right]);
@@ -93,87 +404,123 @@ impl Clothe {
             });
         });
 
-        // Set the size of the springs vector
+        // Create springs
         self.nb_vertices = self.vertices.len() as u32;
-        self.springs = vec![Spring::new(); self.nb_vertices as usize];
-        // Create triangle
+        let mut neighbours = vec![Vec::new(); self.nb_vertices as usize];
         (0..rows).for_each(|row| {
             (0..cols).for_each(|col| {
-                let indice = (rows * row + col) as u32;
-                let mut spring = Spring::new();
-
-                for i in 0..12 {
-                    spring.links[i] = indice;
-                }
+                let indice = rows * row + col;
+                let links = &mut neighbours[indice as usize];
 
                 // If it is not the first row, we add the top parts
                 if row > 0 {
-                    spring.links[1] = indice - cols; // Top
-                    spring.rest_distance[1] = self.get_norm_distance(indice, indice - cols); // Top
                     if col > 0 {
-                        spring.links[0] = indice - 1 - cols; // Top left
-                        spring.rest_distance[0] = self.get_norm_distance(indice, indice - 1 - cols); // Top left
+                        links.push((indice - 1 - cols, SpringKind::Shear)); // Top left
                     }
+                    links.push((indice - cols, SpringKind::Structural)); // Top
                     if col < cols - 1 {
-                        spring.links[2] = indice + 1 - cols; // Top right
-                        spring.rest_distance[2] = self.get_norm_distance(indice, indice + 1 - cols); // Top right
+                        links.push((indice + 1 - cols, SpringKind::Shear)); // Top right
                     }
                 }
 
+                // If it is not the last column, we add `right`
+                if col < cols - 1 {
+                    links.push((indice + 1, SpringKind::Structural)); // Right
+                }
+
                 // If it is not the last row, we can add the bottom part
                 if row < rows - 1 {
-                    spring.links[5] = indice + cols; // Bottom
-                    spring.rest_distance[5] = self.get_norm_distance(indice, indice + cols); // Bottom
-
-                    if col > 0 {
-                        spring.links[6] = indice - 1 + cols; // Bottom left
-                        spring.rest_distance[6] = self.get_norm_distance(indice, indice - 1 + cols); // Bottom left
-                    }
                     if col < cols - 1 {
-                        spring.links[4] = indice + 1 + cols; // Bottom right
-                        spring.rest_distance[4] = self.get_norm_distance(indice, indice + 1 + cols); // Bottom right
+                        links.push((indice + 1 + cols, SpringKind::Shear)); // Bottom right
+                    }
+                    links.push((indice + cols, SpringKind::Structural)); // Bottom
+                    if col > 0 {
+                        links.push((indice - 1 + cols, SpringKind::Shear)); // Bottom left
                     }
                 }
 
                 // If it is not the first column, we add `left`
                 if col > 0 {
-                    spring.links[7] = indice - 1; // Left
-                    spring.rest_distance[7] = self.get_norm_distance(indice, indice - 1);
-                    // Left
-                }
-
-                // If it is not the last column, we add `right`
-                if col < cols - 1 {
-                    spring.links[3] = indice + 1; // Right
-                    spring.rest_distance[3] = self.get_norm_distance(indice, indice + 1);
-                    // Right
+                    links.push((indice - 1, SpringKind::Structural)); // Left
                 }
 
                 // Add blend springs
                 if col > 1 {
-                    spring.links[8] = indice - 2; // Blend left
-                    spring.rest_distance[8] = self.get_norm_distance(indice, indice - 2); // Blend left
+                    links.push((indice - 2, SpringKind::Bend)); // Blend left
                 }
-                if col < cols - 2 {
-                    spring.links[9] = indice + 2; // Blend right
-                    spring.rest_distance[9] = self.get_norm_distance(indice, indice + 2); // Blend right
+                if col + 2 < cols {
+                    links.push((indice + 2, SpringKind::Bend)); // Blend right
                 }
                 if row > 1 {
-                    spring.links[10] = indice - 2 * cols; // Blend top
-                    spring.rest_distance[10] = self.get_norm_distance(indice, indice - 2 * cols); // Blend top
+                    links.push((indice - 2 * cols, SpringKind::Bend)); // Blend top
                 }
-                if row < rows - 2 {
-                    spring.links[11] = indice + 2 * cols; // Blend bottom
-                    spring.rest_distance[11] = self.get_norm_distance(indice, indice + 2 * cols); // Blend bottom
+                if row + 2 < rows {
+                    links.push((indice + 2 * cols, SpringKind::Bend)); // Blend bottom
                 }
-
-                self.springs[indice as usize] = spring;
             });
         });
 
+        self.set_springs(&neighbours);
+
         // dbg!("vertices: {:?}", &self.vertices);
         // dbg!("indices: {:?}", &self.indices);
         // dbg!("springs: {:?}", &self.springs);
         // dbg!("rest_distances: {:?}", &self.rest_distances);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::mem::size_of_val;
+
+    use super::*;
+    use crate::spring::spring_range;
+
+    #[test]
+    fn buffers_beyond_the_device_limits_are_rejected() {
+        assert!(Clothe::check_grid_buffers(8, &wgpu::Limits::default()).is_ok());
+
+        // The springs overflow at about 680x680 squares with the default limits
+        assert!(Clothe::check_grid_buffers(600, &wgpu::Limits::default()).is_ok());
+        assert!(matches!(
+            Clothe::check_grid_buffers(700, &wgpu::Limits::default()),
+            Err(ClotheError::BufferTooLarge { name: "spring", .. }),
+        ));
+        assert!(matches!(Clothe::check_grid_buffers(0, &wgpu::Limits::default()), Err(ClotheError::NoSquare)));
+    }
+
+    #[test]
+    fn checked_spring_count_matches_the_built_clothe() {
+        // Exact for a grid, only the springs overflow with this limit
+        for number_square in [2, 8, 20] {
+            let clothe = Clothe::new(1.0, number_square, &[0.0; 3], &PinSpec::None).unwrap();
+            let size = size_of_val(clothe.springs.as_slice()) as u32;
+            let limits = |size| wgpu::Limits { max_storage_buffer_binding_size: size, ..wgpu::Limits::default() };
+
+            assert!(Clothe::check_grid_buffers(number_square, &limits(size)).is_ok());
+            assert!(Clothe::check_grid_buffers(number_square, &limits(size - 1)).is_err());
+        }
+
+        // An upper bound for a mesh, at most one link per triangle side
+        let mesh = crate::import::parse_obj("v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4\n").unwrap();
+        let clothe = Clothe::from_mesh(&mesh, &[0.0; 3], &PinSpec::None).unwrap();
+        assert!(clothe.springs.len() <= 2 * 3 * mesh.triangles.len());
+    }
+
+    #[test]
+    fn every_spring_is_stored_at_both_ends() {
+        let clothe = Clothe::new(1.0, 8, &[0.0; 3], &PinSpec::None).unwrap();
+        let springs_of = |index: usize| &clothe.springs[spring_range(&clothe.spring_offsets, index)];
+
+        assert_eq!(clothe.spring_offsets.len(), clothe.vertices.len() + 1);
+        assert_eq!(*clothe.spring_offsets.last().unwrap() as usize, clothe.springs.len());
+        for index in 0..clothe.vertices.len() {
+            for spring in springs_of(index) {
+                assert_ne!(spring.link as usize, index);
+                let other_end = springs_of(spring.link as usize).iter().find(|other| other.link as usize == index);
+                let other_end = other_end.unwrap_or_else(|| panic!("{} -> {} has no other end", index, spring.link));
+                assert_eq!((other_end.rest_distance, other_end.kind), (spring.rest_distance, spring.kind));
+            }
+        }
+    }
+}
@@ -0,0 +1,152 @@
+// Signed distances and closest surface points of the colliders, same as
+// `shaders/colliders.wgsl`
+
+use crate::{
+    data_containers::{Capsule, OrientedBox, Plane, Sphere},
+    vector::{add, distance, dot, normalize, quat_conjugate, quat_rotate, scale, sub},
+};
+
+impl Sphere {
+    pub fn center(&self) -> [f32; 3] {
+        [self.x, self.y, self.z]
+    }
+
+    pub fn sdf(&self, p: [f32; 3]) -> f32 {
+        distance(self.center(), p) - self.radius
+    }
+
+    pub fn closest_point(&self, p: [f32; 3]) -> [f32; 3] {
+        add(self.center(), scale(normalize(sub(p, self.center())), self.radius))
+    }
+
+    // Outward normal of the surface closest to the point
+    pub fn normal(&self, p: [f32; 3]) -> [f32; 3] {
+        normalize(sub(p, self.center()))
+    }
+
+    pub fn velocity(&self) -> [f32; 3] {
+        [self.velocity_x, self.velocity_y, self.velocity_z]
+    }
+
+    // Moves the sphere at its velocity for `delta_time` seconds
+    pub fn advance(&mut self, delta_time: f32) {
+        [self.x, self.y, self.z] = add(self.center(), scale(self.velocity(), delta_time));
+    }
+}
+
+impl Plane {
+    pub fn normal(&self) -> [f32; 3] {
+        [self.normal_x, self.normal_y, self.normal_z]
+    }
+
+    pub fn sdf(&self, p: [f32; 3]) -> f32 {
+        dot(self.normal(), p) - self.offset
+    }
+
+    pub fn closest_point(&self, p: [f32; 3]) -> [f32; 3] {
+        sub(p, scale(self.normal(), self.sdf(p)))
+    }
+}
+
+impl OrientedBox {
+    pub fn center(&self) -> [f32; 3] {
+        [self.x, self.y, self.z]
+    }
+
+    pub fn half_extents(&self) -> [f32; 3] {
+        [self.half_x, self.half_y, self.half_z]
+    }
+
+    pub fn rotation(&self) -> [f32; 4] {
+        [self.rotation_x, self.rotation_y, self.rotation_z, self.rotation_w]
+    }
+
+    fn local_point(&self, p: [f32; 3]) -> [f32; 3] {
+        quat_rotate(quat_conjugate(self.rotation()), sub(p, self.center()))
+    }
+
+    pub fn sdf(&self, p: [f32; 3]) -> f32 {
+        let local = self.local_point(p);
+        let half = self.half_extents();
+        let q = [0, 1, 2].map(|i| local[i].abs() - half[i]);
+        let outside = q.map(|v| v.max(0.0));
+
+        dot(outside, outside).sqrt() + q[0].max(q[1].max(q[2])).min(0.0)
+    }
+
+    // Inside points go to the nearest face
+    pub fn closest_point(&self, p: [f32; 3]) -> [f32; 3] {
+        let half = self.half_extents();
+        let mut local = self.local_point(p);
+
+        let clamped = [0, 1, 2].map(|i| local[i].clamp(-half[i], half[i]));
+        if clamped != local {
+            return add(self.center(), quat_rotate(self.rotation(), clamped));
+        }
+
+        let gap = [0, 1, 2].map(|i| half[i] - local[i].abs());
+        let axis = if gap[0] <= gap[1] && gap[0] <= gap[2] {
+            0
+        } else if gap[1] <= gap[2] {
+            1
+        } else {
+            2
+        };
+        local[axis] = if local[axis] >= 0.0 { half[axis] } else { -half[axis] };
+
+        add(self.center(), quat_rotate(self.rotation(), local))
+    }
+
+    // Inside points take the normal of the nearest face
+    pub fn normal(&self, p: [f32; 3]) -> [f32; 3] {
+        let half = self.half_extents();
+        let local = self.local_point(p);
+
+        let clamped = [0, 1, 2].map(|i| local[i].clamp(-half[i], half[i]));
+        if clamped != local {
+            return quat_rotate(self.rotation(), normalize(sub(local, clamped)));
+        }
+
+        let gap = [0, 1, 2].map(|i| half[i] - local[i].abs());
+        let axis = if gap[0] <= gap[1] && gap[0] <= gap[2] {
+            0
+        } else if gap[1] <= gap[2] {
+            1
+        } else {
+            2
+        };
+        let mut normal = [0.0; 3];
+        normal[axis] = if local[axis] >= 0.0 { 1.0 } else { -1.0 };
+
+        quat_rotate(self.rotation(), normal)
+    }
+}
+
+impl Capsule {
+    pub fn start(&self) -> [f32; 3] {
+        [self.start_x, self.start_y, self.start_z]
+    }
+
+    pub fn end(&self) -> [f32; 3] {
+        [self.end_x, self.end_y, self.end_z]
+    }
+
+    fn segment_point(&self, p: [f32; 3]) -> [f32; 3] {
+        let axis = sub(self.end(), self.start());
+        let t = (dot(sub(p, self.start()), axis) / dot(axis, axis).max(1e-12)).clamp(0.0, 1.0);
+
+        add(self.start(), scale(axis, t))
+    }
+
+    pub fn sdf(&self, p: [f32; 3]) -> f32 {
+        distance(self.segment_point(p), p) - self.radius
+    }
+
+    pub fn closest_point(&self, p: [f32; 3]) -> [f32; 3] {
+        let segment_point = self.segment_point(p);
+        add(segment_point, scale(normalize(sub(p, segment_point)), self.radius))
+    }
+    pub fn normal(&self, p: [f32; 3]) -> [f32; 3] {
+        normalize(sub(p, self.segment_point(p)))
+    }
+}
@@ -0,0 +1,207 @@
+use wgpu_bootstrap::winit::{
+    event::{ElementState, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+// Duration of a frame advanced by hand while paused, in seconds
+pub const STEP_FRAME_TIME: f32 = 1.0 / 60.0;
+const MIN_TIME_SCALE: f32 = 1.0 / 16.0;
+const MAX_TIME_SCALE: f32 = 4.0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Step {
+    Frame,
+    Substep,
+}
+
+/// Keyboard control of the simulation time: space pauses, `N` advances one
+/// frame and `M` one substep while paused, `R` resets the clothe, `-` and `=`
+/// halve and double the speed and `Backspace` goes back to real time.
+#[derive(Clone, Debug)]
+pub struct PlaybackControls {
+    pub paused: bool,
+    pub time_scale: f32,
+    step: Option<Step>,
+    reset: bool,
+}
+
+impl Default for PlaybackControls {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            time_scale: 1.0,
+            step: None,
+            reset: false,
+        }
+    }
+}
+
+impl PlaybackControls {
+    /// Updates the controls from a window event, returns whether it was used.
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        let WindowEvent::KeyboardInput { event, .. } = event else {
+            return false;
+        };
+        if event.state != ElementState::Pressed {
+            return false;
+        }
+
+        match event.physical_key {
+            PhysicalKey::Code(KeyCode::Space) => self.paused = !self.paused,
+            PhysicalKey::Code(KeyCode::KeyN) => self.step = Some(Step::Frame),
+            PhysicalKey::Code(KeyCode::KeyM) => self.step = Some(Step::Substep),
+            PhysicalKey::Code(KeyCode::KeyR) => self.reset = true,
+            PhysicalKey::Code(KeyCode::Minus) => {
+                self.time_scale = (self.time_scale / 2.0).max(MIN_TIME_SCALE);
+            }
+            PhysicalKey::Code(KeyCode::Equal) => {
+                self.time_scale = (self.time_scale * 2.0).min(MAX_TIME_SCALE);
+            }
+            PhysicalKey::Code(KeyCode::Backspace) => self.time_scale = 1.0,
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// Whether a reset was asked since the last call.
+    pub fn take_reset(&mut self) -> bool {
+        std::mem::take(&mut self.reset)
+    }
+
+    /// Simulated time to add for this frame, scaled by the time scale.
+    /// Nothing is simulated while paused, unless a step was asked.
+    pub fn advance(&mut self, delta_time: f32) -> Advance {
+        let step = self.step.take();
+        if !self.paused {
+            return Advance::Time(delta_time * self.time_scale);
+        }
+
+        match step {
+            Some(Step::Frame) => Advance::Time(STEP_FRAME_TIME * self.time_scale),
+            Some(Step::Substep) => Advance::Substep,
+            None => Advance::Time(0.0),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Advance {
+    Time(f32),
+    // Exactly one substep, whatever the accumulated time
+    Substep,
+}
+
+// Fraction of a substep still counted as a whole one, so frames lasting an
+// exact number of substeps don't lose one to rounding
+const SUBSTEP_EPSILON: f64 = 1e-4;
+
+/// Fixed substep clock: the frame times are accumulated and consumed by whole
+/// substeps of `time_step` seconds, so the motion doesn't depend on the frame
+/// rate. The time left over is carried to the next frame.
+#[derive(Clone, Debug)]
+pub struct FixedTimestep {
+    pub time_step: f32,
+    accumulator: f64,
+}
+
+impl FixedTimestep {
+    pub fn new(time_step: f32) -> Self {
+        Self {
+            time_step,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Number of substeps to run for `frame_time` more seconds.
+    pub fn advance(&mut self, frame_time: f32) -> u32 {
+        let time_step = self.time_step as f64;
+        self.accumulator += frame_time as f64;
+
+        let substeps = (self.accumulator / time_step + SUBSTEP_EPSILON).floor();
+        self.accumulator = (self.accumulator - substeps * time_step).max(0.0);
+        substeps as u32
+    }
+
+    /// Fraction of a substep accumulated but not simulated yet, between 0 and 1.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.time_step as f64).min(1.0) as f32
+    }
+
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_multiples_give_exactly_that_many_substeps() {
+        let mut timestep = FixedTimestep::new(1.0 / 600.0);
+        for _ in 0..1000 {
+            assert_eq!(timestep.advance(1.0 / 60.0), 10);
+        }
+        assert_eq!(timestep.advance(3.0 / 600.0), 3);
+    }
+
+    #[test]
+    fn leftover_time_is_carried_over() {
+        let mut timestep = FixedTimestep::new(0.01);
+        assert_eq!(timestep.advance(0.015), 1);
+        assert!((timestep.alpha() - 0.5).abs() < 1e-4);
+        assert_eq!(timestep.advance(0.015), 2);
+        assert!(timestep.alpha() < 1e-4);
+        assert_eq!(timestep.advance(0.004), 0);
+        assert_eq!(timestep.advance(0.004), 0);
+        assert_eq!(timestep.advance(0.004), 1);
+    }
+
+    #[test]
+    fn alpha_stays_between_0_and_1() {
+        let mut timestep = FixedTimestep::new(1.0 / 240.0);
+        for i in 0..500 {
+            // Irregular frame times, from nothing to a few substeps
+            timestep.advance((i * 7 % 13) as f32 * 0.002);
+            let alpha = timestep.alpha();
+            assert!((0.0..=1.0).contains(&alpha), "alpha {alpha} at frame {i}");
+        }
+        timestep.reset();
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+
+    #[test]
+    fn paused_steps_advance_one_frame_or_one_substep() {
+        let mut controls = PlaybackControls {
+            paused: true,
+            ..Default::default()
+        };
+        assert_eq!(controls.advance(0.1), Advance::Time(0.0));
+
+        controls.step = Some(Step::Frame);
+        assert_eq!(controls.advance(0.1), Advance::Time(STEP_FRAME_TIME));
+        assert_eq!(controls.advance(0.1), Advance::Time(0.0));
+
+        controls.step = Some(Step::Substep);
+        assert_eq!(controls.advance(0.1), Advance::Substep);
+        assert_eq!(controls.advance(0.1), Advance::Time(0.0));
+
+        // A stepped frame lasts exactly one frame of substeps
+        let mut timestep = FixedTimestep::new(STEP_FRAME_TIME / 4.0);
+        controls.step = Some(Step::Frame);
+        let Advance::Time(time) = controls.advance(0.1) else {
+            panic!("a frame step should advance the time");
+        };
+        assert_eq!(timestep.advance(time), 4);
+    }
+
+    #[test]
+    fn running_controls_scale_the_frame_time() {
+        let mut controls = PlaybackControls {
+            time_scale: 0.5,
+            ..Default::default()
+        };
+        assert_eq!(controls.advance(0.1), Advance::Time(0.05));
+    }
+}
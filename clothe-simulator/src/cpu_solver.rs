@@ -0,0 +1,1018 @@
+//! Pure Rust version of the compute shaders, used to step the clothe on
+//! machines without a GPU. Every pass reads the state left by the previous
+//! pass, in the same order as `MyApp::update`: distance, wind, compute, self
+//! collision, normal.
+
+use std::collections::HashMap;
+
+use crate::{
+    clothe::{Clothe, ClotheError},
+    data_containers::{
+        Capsule, ClotheData, Colliders, ComputeData, OrientedBox, Plane, SelfCollisionData, Sphere, WindData,
+    },
+    export::unique_triangles,
+    integrator::{Integrator, IntegratorState, Solver},
+    node::Node,
+    scene::Scene,
+    spring::{spring_range, Spring, SpringDirection, SpringKind},
+    vector::{add, cross, dot, length, normalize, scale, sub},
+    xpbd::XpbdState,
+};
+
+fn xyz(v: &[f32; 4]) -> [f32; 3] {
+    [v[0], v[1], v[2]]
+}
+
+fn set_xyz(v: &mut [f32; 4], value: [f32; 3]) {
+    v[..3].copy_from_slice(&value);
+}
+
+/// Same as `distance_shader.wgsl`: update `current_distance` of every spring
+/// and break the springs stretched beyond the tear strain.
+pub fn compute_distances(vertices: &[Node], offsets: &[u32], springs: &mut [Spring], clothe_data: &ClotheData) {
+    let nb_vertices = (clothe_data.nb_vertices as usize).min(vertices.len()).min(offsets.len() - 1);
+
+    for index in 0..nb_vertices {
+        let position = xyz(&vertices[index].position);
+
+        for spring in &mut springs[spring_range(offsets, index)] {
+            let link = spring.link as usize;
+
+            if link == index {
+                continue;
+            }
+
+            let current_distance = length(sub(xyz(&vertices[link].position), position));
+            spring.current_distance = current_distance;
+
+            let strain = current_distance / spring.rest_distance - 1.0;
+            if clothe_data.tear_strain > 0.0 && strain > clothe_data.tear_strain {
+                spring.link = index as u32;
+            }
+        }
+    }
+}
+
+// Smooth time varying gusts, between -1 and 1 on each axis
+fn gust(p: [f32; 3], t: f32) -> [f32; 3] {
+    [
+        0.5 * ((1.7 * p[1] + 2.3 * t).sin() + (1.1 * p[2] + 1.3 * t).sin()),
+        0.5 * ((1.3 * p[2] + 1.9 * t).sin() + (0.9 * p[0] + 2.9 * t).sin()),
+        0.5 * ((1.9 * p[0] + 1.7 * t).sin() + (1.5 * p[1] + 2.1 * t).sin()),
+    ]
+}
+
+/// Same as `wind.wgsl`: drag and lift of every triangle, shared by its
+/// vertices and added to their velocity.
+pub fn apply_wind(
+    vertices: &mut [Node],
+    masses: &[f32],
+    triangles: &[[u32; 3]],
+    wind: &WindData,
+    data: &ComputeData,
+) {
+    let mut forces = vec![[0.0; 3]; vertices.len()];
+
+    for triangle in triangles {
+        let [v0, v1, v2] = triangle.map(|index| &vertices[index as usize]);
+
+        let center = scale(add(add(xyz(&v0.position), xyz(&v1.position)), xyz(&v2.position)), 1.0 / 3.0);
+        let velocity = scale(add(add(xyz(&v0.velocity), xyz(&v1.velocity)), xyz(&v2.velocity)), 1.0 / 3.0);
+        let air_velocity = add(
+            [wind.velocity_x, wind.velocity_y, wind.velocity_z],
+            scale(gust(center, wind.time), wind.turbulence),
+        );
+
+        let relative = sub(air_velocity, velocity);
+        let speed = length(relative);
+        let area_normal = cross(
+            sub(xyz(&v1.position), xyz(&v0.position)),
+            sub(xyz(&v2.position), xyz(&v0.position)),
+        );
+        let area = 0.5 * length(area_normal);
+
+        if speed < 1e-6 || area < 1e-12 {
+            continue;
+        }
+
+        let direction = scale(relative, 1.0 / speed);
+        let mut normal = normalize(area_normal);
+        if dot(normal, direction) < 0.0 {
+            normal = scale(normal, -1.0);
+        }
+        let cos_angle = dot(normal, direction);
+        let pressure = 0.5 * wind.air_density * speed * speed * area;
+
+        let lift_direction = sub(normal, scale(direction, cos_angle));
+        let force = scale(
+            add(scale(direction, wind.drag_coefficient), scale(lift_direction, wind.lift_coefficient)),
+            pressure * cos_angle / 3.0,
+        );
+
+        for index in triangle {
+            forces[*index as usize] = add(forces[*index as usize], force);
+        }
+    }
+
+    for ((vertex, force), mass) in vertices.iter_mut().zip(forces).zip(masses) {
+        if vertex.pinned == 0 {
+            let velocity = add(xyz(&vertex.velocity), scale(force, data.delta_time / mass));
+            set_xyz(&mut vertex.velocity, velocity);
+        }
+    }
+}
+
+// Remove the tangential part of the resultant, up to the friction limit.
+// `normal` is the normal of the collider surface.
+fn friction(resultant: [f32; 3], normal: [f32; 3], friction_factor: f32) -> [f32; 3] {
+    let r_n = scale(normal, dot(resultant, normal));
+    let r_t = sub(resultant, r_n);
+    if length(r_t) == 0.0 {
+        return resultant;
+    }
+    let friction = length(r_t).min(friction_factor * length(r_n));
+
+    sub(resultant, scale(normalize(r_t), friction))
+}
+
+// Move the vertex on the collider surface
+fn project(position: &mut [f32; 3], velocity: &mut [f32; 3], surface_point: [f32; 3], delta_time: f32) {
+    *velocity = scale(sub(surface_point, *position), 1.0 / delta_time);
+    *position = surface_point;
+}
+
+// Same as `project_moving` in contacts.wgsl
+fn project_moving(position: &mut [f32; 3], velocity: &mut [f32; 3], surface_point: [f32; 3], normal: [f32; 3],
+    collider_velocity: [f32; 3], friction_factor: f32, delta_time: f32) {
+    let start = *velocity;
+    project(position, velocity, surface_point, delta_time);
+    let tangential = sub(collider_velocity, scale(normal, dot(collider_velocity, normal)));
+    if length(tangential) == 0.0 {
+        return;
+    }
+
+    let normal_change = dot(sub(*velocity, start), normal).max(0.0);
+    let drag = (friction_factor * normal_change / length(tangential)).min(1.0);
+
+    *velocity = add(*velocity, scale(tangential, drag));
+}
+
+// Same as `spring_stiffness` and `spring_damping` in the shaders
+fn spring_stiffness(data: &ComputeData, spring: &Spring) -> f32 {
+    let kind = SpringKind::from_index(spring.kind);
+    let kind_factor = match kind {
+        SpringKind::Structural => data.structural_stiffness,
+        SpringKind::Shear => data.shear_stiffness,
+        SpringKind::Bend => data.bend_stiffness,
+    };
+    let direction_factor = match (kind, SpringDirection::from_index(spring.direction)) {
+        (SpringKind::Bend, SpringDirection::Warp) => data.warp_bend,
+        (SpringKind::Bend, SpringDirection::Weft) => data.weft_bend,
+        (SpringKind::Bend, SpringDirection::Bias) => data.bias_bend,
+        (_, SpringDirection::Warp) => data.warp_stretch,
+        (_, SpringDirection::Weft) => data.weft_stretch,
+        (_, SpringDirection::Bias) => data.bias_stretch,
+    };
+
+    kind_factor * direction_factor
+}
+
+fn spring_damping(data: &ComputeData, kind: u32) -> f32 {
+    match SpringKind::from_index(kind) {
+        SpringKind::Structural => data.structural_damping,
+        SpringKind::Shear => data.shear_damping,
+        SpringKind::Bend => data.bend_damping,
+    }
+}
+
+// Everything `compute.wgsl` reads besides the vertices
+struct Forces<'a> {
+    offsets: &'a [u32],
+    springs: &'a [Spring],
+    masses: &'a [f32],
+    spheres: &'a [Sphere],
+    boxes: &'a [OrientedBox],
+    capsules: &'a [Capsule],
+    plane: &'a Plane,
+    data: &'a ComputeData,
+    clothe_data: &'a ClotheData,
+}
+
+impl<'a> Forces<'a> {
+    fn new(offsets: &'a [u32], springs: &'a [Spring], masses: &'a [f32], colliders: &'a Colliders,
+        data: &'a ComputeData, clothe_data: &'a ClotheData) -> Self {
+        Self {
+            offsets,
+            springs,
+            masses,
+            spheres: &colliders.spheres[..(data.nb_spheres as usize).min(colliders.spheres.len())],
+            boxes: &colliders.boxes[..(data.nb_boxes as usize).min(colliders.boxes.len())],
+            capsules: &colliders.capsules[..(data.nb_capsules as usize).min(colliders.capsules.len())],
+            plane: &colliders.plane,
+            data,
+            clothe_data,
+        }
+    }
+
+    // Same as `compute_resultant`, the linked vertices are read from `vertices`
+    fn resultant(&self, vertices: &[Node], index: usize, position: [f32; 3], velocity: [f32; 3]) -> [f32; 3] {
+        let mut resultant = [0.0; 3];
+        let mut damping_sum = 0.0;
+        let mut nb_springs = 0.0;
+
+        // Loop on every spring of the vertex
+        for spring in &self.springs[spring_range(self.offsets, index)] {
+            let link = spring.link as usize;
+
+            if link == index {
+                continue;
+            }
+
+            let link_position = xyz(&vertices[link].position);
+            let norm = (length(sub(link_position, position)) - spring.rest_distance) * self.data.spring_contant
+                * spring_stiffness(self.data, spring);
+            let spring_force = scale(sub(link_position, position), norm);
+
+            resultant = add(resultant, spring_force);
+            damping_sum += spring_damping(self.data, spring.kind);
+            nb_springs += 1.0;
+        }
+
+        // Weight and damping are applied once per vertex
+        let mut damping = self.data.damping_factor;
+        if nb_springs > 0.0 {
+            damping *= damping_sum / nb_springs;
+        }
+        resultant = sub(resultant, scale(velocity, damping));
+        resultant[1] += self.data.gravity * self.masses[index];
+
+        // Add friction with the colliders in contact
+        self.contact_friction(resultant, position)
+    }
+
+    // Same as `contact_friction` in contacts.wgsl
+    fn contact_friction(&self, mut resultant: [f32; 3], position: [f32; 3]) -> [f32; 3] {
+        for sphere in self.spheres.iter().filter(|sphere| sphere.sdf(position) <= 0.0) {
+            resultant = friction(resultant, sphere.normal(position), sphere.friction_factor);
+        }
+        for b in self.boxes.iter().filter(|b| b.sdf(position) <= 0.0) {
+            resultant = friction(resultant, b.normal(position), b.friction_factor);
+        }
+        for capsule in self.capsules.iter().filter(|capsule| capsule.sdf(position) <= 0.0) {
+            resultant = friction(resultant, capsule.normal(position), capsule.friction_factor);
+        }
+        if self.plane.enabled != 0 && self.plane.sdf(position) <= 0.0 {
+            resultant = friction(resultant, self.plane.normal(), self.plane.friction_factor);
+        }
+
+        resultant
+    }
+
+    // Colliders collision
+    fn collide(&self, position: &mut [f32; 3], velocity: &mut [f32; 3]) {
+        let delta_time = self.data.delta_time;
+
+        for sphere in self.spheres {
+            if sphere.sdf(*position) < 0.0 {
+                let surface_point = sphere.closest_point(*position);
+                let normal = normalize(sub(surface_point, sphere.center()));
+                project_moving(position, velocity, surface_point, normal, sphere.velocity(), sphere.friction_factor,
+                    delta_time);
+            }
+        }
+        for b in self.boxes {
+            if b.sdf(*position) < 0.0 {
+                project(position, velocity, b.closest_point(*position), delta_time);
+            }
+        }
+        for capsule in self.capsules {
+            if capsule.sdf(*position) < 0.0 {
+                project(position, velocity, capsule.closest_point(*position), delta_time);
+            }
+        }
+        if self.plane.enabled != 0 && self.plane.sdf(*position) < 0.0 {
+            project(position, velocity, self.plane.closest_point(*position), delta_time);
+        }
+    }
+
+    fn store(&self, node: &mut Node, mut position: [f32; 3], mut velocity: [f32; 3], resultant: [f32; 3]) {
+        self.collide(&mut position, &mut velocity);
+
+        node.resultant = resultant;
+        set_xyz(&mut node.velocity, velocity);
+        set_xyz(&mut node.position, position);
+    }
+
+    // Derivative of every vertex for the current state of `vertices`
+    fn derivatives(&self, vertices: &[Node]) -> Vec<([f32; 3], [f32; 3])> {
+        let nb_vertices = (self.clothe_data.nb_vertices as usize).min(vertices.len()).min(self.offsets.len() - 1);
+
+        (0..nb_vertices)
+            .map(|index| {
+                let vertex = &vertices[index];
+                let velocity = xyz(&vertex.velocity);
+
+                if vertex.pinned != 0 {
+                    return (velocity, [0.0; 3]);
+                }
+
+                let resultant = self.resultant(vertices, index, xyz(&vertex.position), velocity);
+                (velocity, scale(resultant, 1.0 / self.masses[index]))
+            })
+            .collect()
+    }
+}
+
+// Pinned vertices never move
+fn fix(node: &mut Node) {
+    set_xyz(&mut node.velocity, [0.0; 3]);
+    node.resultant = [0.0; 3];
+}
+
+/// Same as the entry points of `compute.wgsl` for `integrator`:
+/// accumulate the forces, integrate and resolve the collisions. Pinned
+/// vertices are left in place. `states` is only used by Verlet and RK4.
+pub fn compute_forces(
+    clothe: &mut Clothe,
+    states: &mut [IntegratorState],
+    colliders: &Colliders,
+    data: &ComputeData,
+    clothe_data: &ClotheData,
+    integrator: Integrator,
+) {
+    let Clothe { vertices, spring_offsets: offsets, springs, masses, .. } = clothe;
+    let forces = Forces::new(offsets, springs, masses, colliders, data, clothe_data);
+    let nb_vertices = (clothe_data.nb_vertices as usize).min(vertices.len()).min(offsets.len() - 1);
+    let delta_time = data.delta_time;
+
+    match integrator {
+        Integrator::SymplecticEuler => {
+            let previous = vertices.to_vec();
+
+            for (index, vertex) in previous.iter().enumerate().take(nb_vertices) {
+                if vertex.pinned != 0 {
+                    fix(&mut vertices[index]);
+                    continue;
+                }
+
+                let position = xyz(&vertex.position);
+                let velocity = xyz(&vertex.velocity);
+                let resultant = forces.resultant(&previous, index, position, velocity);
+
+                // New velocities and positions
+                let velocity = add(velocity, scale(resultant, delta_time / masses[index]));
+                let position = add(position, scale(velocity, delta_time));
+
+                forces.store(&mut vertices[index], position, velocity, resultant);
+            }
+        }
+        Integrator::Verlet => {
+            let previous = vertices.to_vec();
+
+            for (index, vertex) in previous.iter().enumerate().take(nb_vertices) {
+                let position = xyz(&vertex.position);
+
+                if vertex.pinned != 0 {
+                    set_xyz(&mut states[index].position, position);
+                    fix(&mut vertices[index]);
+                    continue;
+                }
+
+                let resultant = forces.resultant(&previous, index, position, xyz(&vertex.velocity));
+                let mut new_position = add(
+                    sub(scale(position, 2.0), xyz(&states[index].position)),
+                    scale(resultant, delta_time * delta_time / masses[index]),
+                );
+                let mut velocity = scale(sub(new_position, position), 1.0 / delta_time);
+                forces.collide(&mut new_position, &mut velocity);
+
+                // The previous position follows the velocity given by the colliders
+                set_xyz(&mut states[index].position, sub(new_position, scale(velocity, delta_time)));
+
+                let node = &mut vertices[index];
+                node.resultant = resultant;
+                set_xyz(&mut node.velocity, velocity);
+                set_xyz(&mut node.position, new_position);
+            }
+        }
+        Integrator::Rk4 => {
+            let start = vertices.to_vec();
+            let mut sum_position = vec![[0.0; 3]; nb_vertices];
+            let mut sum_velocity = vec![[0.0; 3]; nb_vertices];
+
+            // Each stage evaluates the derivatives, then moves the vertices to
+            // the start of the substep plus `factor` times these derivatives
+            for (weight, factor) in [(1.0, 0.5), (2.0, 0.5), (2.0, 1.0), (1.0, 0.0)] {
+                let derivatives = forces.derivatives(vertices);
+
+                for (index, (k_position, k_velocity)) in derivatives.into_iter().enumerate() {
+                    sum_position[index] = add(sum_position[index], scale(k_position, weight));
+                    sum_velocity[index] = add(sum_velocity[index], scale(k_velocity, weight));
+
+                    let state = &mut states[index];
+                    set_xyz(&mut state.k_position, k_position);
+                    set_xyz(&mut state.k_velocity, k_velocity);
+
+                    if factor > 0.0 && start[index].pinned == 0 {
+                        let position = add(xyz(&start[index].position), scale(k_position, factor * delta_time));
+                        let velocity = add(xyz(&start[index].velocity), scale(k_velocity, factor * delta_time));
+                        set_xyz(&mut vertices[index].position, position);
+                        set_xyz(&mut vertices[index].velocity, velocity);
+                    }
+                }
+            }
+
+            for index in 0..nb_vertices {
+                let vertex = &start[index];
+                states[index].position = vertex.position;
+                states[index].velocity = vertex.velocity;
+                set_xyz(&mut states[index].sum_position, sum_position[index]);
+                set_xyz(&mut states[index].sum_velocity, sum_velocity[index]);
+
+                if vertex.pinned != 0 {
+                    fix(&mut vertices[index]);
+                    continue;
+                }
+
+                let position = add(xyz(&vertex.position), scale(sum_position[index], delta_time / 6.0));
+                let velocity = add(xyz(&vertex.velocity), scale(sum_velocity[index], delta_time / 6.0));
+                let resultant = scale(sum_velocity[index], masses[index] / 6.0);
+
+                forces.store(&mut vertices[index], position, velocity, resultant);
+            }
+        }
+    }
+}
+
+/// Same as the entry points of `xpbd.wgsl`: predict the positions with the
+/// external forces, project the links as distance constraints with Jacobi
+/// iterations, then derive the velocities and resolve the collisions.
+pub fn solve_xpbd(
+    clothe: &mut Clothe,
+    colliders: &Colliders,
+    data: &ComputeData,
+    clothe_data: &ClotheData,
+    constraint_iterations: u32,
+) {
+    // Same as `RELAXATION` in `xpbd.wgsl`
+    const RELAXATION: f32 = 1.5;
+
+    let Clothe { vertices, spring_offsets: offsets, springs, masses, .. } = clothe;
+    let forces = Forces::new(offsets, springs, masses, colliders, data, clothe_data);
+    let nb_vertices = (clothe_data.nb_vertices as usize).min(vertices.len()).min(offsets.len() - 1);
+    let delta_time = data.delta_time;
+    let inverse_mass = |vertex: &Node, mass: f32| if vertex.pinned != 0 { 0.0 } else { 1.0 / mass };
+    // The memory of the solver is reset at each substep, it doesn't need to be kept
+    let mut states: Vec<XpbdState> = vertices.iter().map(XpbdState::new).collect();
+    let mut lambdas = vec![0.0; springs.len()];
+
+    // Predict
+    for ((vertex, state), &mass) in vertices.iter_mut().zip(states.iter_mut()).zip(masses.iter()).take(nb_vertices) {
+        state.previous_position = vertex.position;
+
+        if vertex.pinned != 0 {
+            fix(vertex);
+            continue;
+        }
+
+        let velocity = xyz(&vertex.velocity);
+        let resultant = sub([0.0, data.gravity * mass, 0.0], scale(velocity, data.damping_factor));
+        let resultant = forces.contact_friction(resultant, xyz(&vertex.position));
+
+        let velocity = add(velocity, scale(resultant, delta_time / mass));
+        let position = add(xyz(&vertex.position), scale(velocity, delta_time));
+        set_xyz(&mut vertex.velocity, velocity);
+        set_xyz(&mut vertex.position, position);
+        vertex.resultant = resultant;
+    }
+
+    for _ in 0..constraint_iterations {
+        // Solve the constraints from the same positions
+        for index in 0..nb_vertices {
+            let position = xyz(&vertices[index].position);
+            let inverse_mass_vertex = inverse_mass(&vertices[index], masses[index]);
+            let mut correction = [0.0; 3];
+            let mut nb_constraints = 0.0;
+
+            for i in spring_range(offsets, index) {
+                let link = springs[i].link as usize;
+                if link == index {
+                    continue;
+                }
+
+                let offset = sub(position, xyz(&vertices[link].position));
+                let current_distance = length(offset);
+                let inverse_masses = inverse_mass_vertex + inverse_mass(&vertices[link], masses[link]);
+                let stiffness = spring_stiffness(data, &springs[i]);
+                if current_distance < 1e-9 || inverse_masses == 0.0 || stiffness <= 0.0 {
+                    continue;
+                }
+
+                let alpha = data.compliance / (stiffness * delta_time * delta_time);
+                let constraint = current_distance - springs[i].rest_distance;
+                let delta_lambda = (-constraint - alpha * lambdas[i]) / (inverse_masses + alpha);
+                lambdas[i] += delta_lambda;
+
+                correction = add(correction, scale(offset, inverse_mass_vertex * delta_lambda / current_distance));
+                nb_constraints += 1.0;
+            }
+
+            set_xyz(&mut states[index].correction, scale(correction, RELAXATION / f32::max(nb_constraints, 1.0)));
+        }
+
+        for (vertex, state) in vertices.iter_mut().zip(states.iter()).take(nb_vertices) {
+            let position = add(xyz(&vertex.position), xyz(&state.correction));
+            set_xyz(&mut vertex.position, position);
+        }
+    }
+
+    // Finalize
+    for (vertex, state) in vertices.iter_mut().zip(states.iter()).take(nb_vertices) {
+        if vertex.pinned != 0 {
+            continue;
+        }
+
+        let mut position = xyz(&vertex.position);
+        let mut velocity = scale(sub(position, xyz(&state.previous_position)), 1.0 / delta_time);
+        forces.collide(&mut position, &mut velocity);
+
+        set_xyz(&mut vertex.velocity, velocity);
+        set_xyz(&mut vertex.position, position);
+    }
+}
+
+/// Same as `self_collision.wgsl`: push apart the vertices closer than the
+/// thickness that are not linked by a spring. The grid is exact here, no
+/// bucket is ever full.
+pub fn self_collision(vertices: &mut [Node], offsets: &[u32], springs: &[Spring], data: &SelfCollisionData) {
+    let nb_vertices = (data.nb_vertices as usize).min(vertices.len());
+    let cell_of = |position: [f32; 3]| position.map(|x| (x / data.thickness).floor() as i32);
+
+    let mut cells: HashMap<[i32; 3], Vec<usize>> = HashMap::new();
+    for (index, vertex) in vertices.iter().enumerate().take(nb_vertices) {
+        cells.entry(cell_of(xyz(&vertex.position))).or_default().push(index);
+    }
+
+    let is_linked = |index: usize, other: usize| {
+        springs[spring_range(offsets, index)].iter().any(|spring| spring.link as usize == other)
+    };
+
+    let mut corrections = vec![[0.0; 3]; nb_vertices];
+    for (index, correction) in corrections.iter_mut().enumerate() {
+        let vertex = &vertices[index];
+        if vertex.pinned != 0 {
+            continue;
+        }
+
+        let position = xyz(&vertex.position);
+        let cell = cell_of(position);
+
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let Some(others) = cells.get(&[cell[0] + dx, cell[1] + dy, cell[2] + dz]) else {
+                        continue;
+                    };
+
+                    for &other in others {
+                        if other == index || is_linked(index, other) {
+                            continue;
+                        }
+
+                        let offset = sub(position, xyz(&vertices[other].position));
+                        let current_distance = length(offset);
+
+                        if current_distance < data.thickness && current_distance > 0.0 {
+                            let push = (data.thickness - current_distance) * 0.5 / current_distance;
+                            *correction = add(*correction, scale(offset, push));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    for (vertex, correction) in vertices.iter_mut().zip(corrections) {
+        let position = add(xyz(&vertex.position), correction);
+        set_xyz(&mut vertex.position, position);
+    }
+}
+
+// Every edge of an intact triangle is a spring
+fn is_torn(triangle: [u32; 3], offsets: &[u32], springs: &[Spring]) -> bool {
+    let is_linked = |index: u32, other: u32| {
+        springs[spring_range(offsets, index as usize)].iter().any(|spring| spring.link == other)
+    };
+    let [a, b, c] = triangle;
+
+    !(is_linked(a, b) && is_linked(b, c) && is_linked(c, a))
+}
+
+/// Same as `triangle_mask.wgsl`: the triangles with a broken edge become
+/// degenerate.
+pub fn mask_torn_triangles(indices: &[u32], offsets: &[u32], springs: &[Spring]) -> Vec<u32> {
+    indices
+        .chunks_exact(3)
+        .flat_map(|t| {
+            if is_torn([t[0], t[1], t[2]], offsets, springs) {
+                [t[0]; 3]
+            } else {
+                [t[0], t[1], t[2]]
+            }
+        })
+        .collect()
+}
+
+/// Same as `normal_shader.wgsl`: sum of the normals of the triangles around
+/// each vertex, weighted by their area. The torn triangles are skipped.
+pub fn compute_normals(vertices: &mut [Node], triangles: &[[u32; 3]], offsets: &[u32], springs: &[Spring],
+    clothe_data: &ClotheData) {
+    let nb_vertices = (clothe_data.nb_vertices as usize).min(vertices.len());
+    let mut normals = vec![[0.0; 3]; nb_vertices];
+
+    for triangle in triangles.iter().filter(|&&triangle| !is_torn(triangle, offsets, springs)) {
+        let [a, b, c] = triangle.map(|index| xyz(&vertices[index as usize].position));
+        let normal = cross(sub(b, a), sub(c, a));
+
+        for &index in triangle {
+            if let Some(sum) = normals.get_mut(index as usize) {
+                *sum = add(*sum, normal);
+            }
+        }
+    }
+
+    for (vertex, normal) in vertices.iter_mut().zip(normals) {
+        if normal != [0.0; 3] {
+            set_xyz(&mut vertex.normal, normalize(normal));
+        }
+    }
+}
+
+/// Clothe of a scene stepped on the CPU, with the per vertex memory of the
+/// solver.
+pub struct CpuSolver {
+    pub clothe: Clothe,
+    pub time: f32,
+    colliders: Colliders,
+    clothe_data: ClotheData,
+    self_collision: Option<SelfCollisionData>,
+    wind: Option<WindData>,
+    triangles: Vec<[u32; 3]>,
+    solver: Solver,
+    integrator: Integrator,
+    constraint_iterations: u32,
+    integrator_states: Vec<IntegratorState>,
+}
+
+impl CpuSolver {
+    pub fn new(scene: &Scene) -> Result<Self, ClotheError> {
+        let clothe = scene.build_clothe()?;
+        let triangles = unique_triangles(&clothe.indices);
+
+        Ok(Self {
+            time: 0.0,
+            colliders: scene.colliders(),
+            clothe_data: scene.clothe_data(&clothe),
+            self_collision: scene.self_collision_data(&clothe),
+            wind: scene.wind_data(triangles.len() as u32),
+            solver: scene.simulation.solver,
+            integrator: scene.simulation.integrator,
+            constraint_iterations: scene.simulation.constraint_iterations,
+            integrator_states: clothe.vertices.iter().map(IntegratorState::new).collect(),
+            triangles,
+            clothe,
+        })
+    }
+
+    /// Replace the spheres, e.g. with the next frame of a `SphereAnimator`.
+    pub fn set_spheres(&mut self, spheres: Vec<Sphere>) {
+        self.colliders.spheres = spheres;
+    }
+
+    /// Run one substep on the clothe, `data.delta_time` is the substep duration.
+    pub fn step(&mut self, data: &ComputeData) {
+        let clothe = &mut self.clothe;
+        let clothe_data = &self.clothe_data;
+
+        // Same as sphere_motion.wgsl
+        for sphere in &mut self.colliders.spheres {
+            sphere.advance(data.delta_time);
+        }
+
+        compute_distances(&clothe.vertices, &clothe.spring_offsets, &mut clothe.springs, clothe_data);
+        if let Some(wind) = &mut self.wind {
+            wind.time = self.time;
+            apply_wind(&mut clothe.vertices, &clothe.masses, &self.triangles, wind, data);
+        }
+        match self.solver {
+            Solver::MassSpring => compute_forces(clothe, &mut self.integrator_states, &self.colliders, data,
+                clothe_data, self.integrator),
+            Solver::Xpbd => solve_xpbd(clothe, &self.colliders, data, clothe_data, self.constraint_iterations),
+        }
+        if let Some(self_collision_data) = &self.self_collision {
+            self_collision(&mut clothe.vertices, &clothe.spring_offsets, &clothe.springs, self_collision_data);
+        }
+        compute_normals(&mut clothe.vertices, &self.triangles, &clothe.spring_offsets, &clothe.springs,
+            clothe_data);
+
+        self.time += data.delta_time;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytemuck::Zeroable;
+
+    use super::*;
+    use crate::{
+        clothe::PinSpec,
+        integrator::Solver,
+        scene::{BoxConfig, CapsuleConfig, PlaneConfig, SphereConfig, WindConfig},
+    };
+
+    // Small clothe without collider, so the tests run quickly
+    fn scene() -> Scene {
+        let mut scene = Scene::default();
+        scene.clothe.number_squares = 8;
+        scene.clothe.size = 1.0;
+        scene.spheres.clear();
+        scene
+    }
+
+    fn run(scene: &Scene, substeps: u32) -> CpuSolver {
+        let mut solver = CpuSolver::new(scene).unwrap();
+        let data = scene.compute_data(scene.time_step());
+        for _ in 0..substeps {
+            solver.step(&data);
+        }
+        solver
+    }
+
+    #[test]
+    fn free_falling_clothe_gains_downward_velocity() {
+        let solver = run(&scene(), 50);
+
+        for vertex in &solver.clothe.vertices {
+            assert!(vertex.velocity[1] < 0.0, "velocity {:?}", vertex.velocity);
+        }
+    }
+
+    #[test]
+    fn pinned_vertices_keep_their_position() {
+        let mut scene = scene();
+        scene.clothe.pins = PinSpec::TopCorners;
+        let start = scene.build_clothe().unwrap().vertices;
+        let solver = run(&scene, 300);
+
+        let pinned: Vec<usize> = (0..start.len()).filter(|&i| start[i].pinned != 0).collect();
+        assert_eq!(pinned.len(), 2);
+        for index in pinned {
+            assert_eq!(solver.clothe.vertices[index].position, start[index].position);
+        }
+    }
+
+    #[test]
+    fn no_vertex_ends_inside_a_sphere() {
+        let mut scene = scene();
+        scene.clothe.center = [0.0, 0.6, 0.0];
+        scene.spheres.push(SphereConfig { radius: 0.5, ..SphereConfig::default() });
+        let solver = run(&scene, 3000);
+        let sphere = &scene.spheres()[0];
+        let distances: Vec<f32> = solver.clothe.vertices.iter().map(|v| sphere.sdf(xyz(&v.position))).collect();
+
+        assert!(distances.iter().any(|&distance| distance < 1e-3), "the clothe didn't reach the sphere");
+        assert!(distances.iter().all(|&distance| distance >= -1e-4), "distances {:?}", distances);
+    }
+
+    #[test]
+    fn every_vertex_weighs_its_mass_whatever_its_springs() {
+        let mut scene = scene();
+        scene.clothe.density = Some(150.0);
+        let solver = run(&scene, 1);
+
+        // From rest, only the weight accelerates the vertices during the first substep
+        let expected = scene.simulation.gravity * scene.time_step();
+        for vertex in &solver.clothe.vertices {
+            assert!((vertex.velocity[1] - expected).abs() < 1e-6, "velocity {:?}", vertex.velocity);
+        }
+
+        // And the total mass doesn't depend on the resolution
+        let total_mass = |number_squares| {
+            let mut scene = scene.clone();
+            scene.clothe.number_squares = number_squares;
+            scene.build_clothe().unwrap().masses.iter().sum::<f32>()
+        };
+        assert!((total_mass(8) - total_mass(20)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn ground_friction_splits_the_forces_along_the_plane_normal() {
+        let mut scene = scene();
+        scene.plane = Some(PlaneConfig { offset: 0.0, friction_factor: 0.5, ..PlaneConfig::default() });
+        let clothe = scene.build_clothe().unwrap();
+        let colliders = scene.colliders();
+        let data = scene.compute_data(scene.time_step());
+        let clothe_data = scene.clothe_data(&clothe);
+        let forces = Forces::new(&clothe.spring_offsets, &clothe.springs, &clothe.masses, &colliders, &data,
+            &clothe_data);
+
+        // The tangential force is held up to half the normal force
+        assert_eq!(forces.contact_friction([1.0, -10.0, 0.0], [0.0, 0.0, 0.0]), [0.0, -10.0, 0.0]);
+        assert_eq!(forces.contact_friction([8.0, -10.0, 0.0], [0.0, 0.0, 0.0]), [3.0, -10.0, 0.0]);
+        // A force along the normal is left as is
+        assert_eq!(forces.contact_friction([0.0, -10.0, 0.0], [0.0, 0.0, 0.0]), [0.0, -10.0, 0.0]);
+    }
+
+    #[test]
+    fn no_vertex_ends_inside_a_box_or_a_capsule() {
+        let mut scene = scene();
+        scene.clothe.center = [0.0, 0.6, 0.0];
+        scene.boxes.push(BoxConfig {
+            center: [-0.25, 0.0, 0.0],
+            half_extents: [0.2, 0.2, 0.3],
+            rotation_angle: 30.0,
+            ..BoxConfig::default()
+        });
+        scene.capsules.push(CapsuleConfig {
+            start: [0.2, 0.3, -0.4],
+            end: [0.2, 0.3, 0.4],
+            radius: 0.1,
+            ..CapsuleConfig::default()
+        });
+        let solver = run(&scene, 4000);
+        let (b, capsule) = (&scene.boxes()[0], &scene.capsules()[0]);
+
+        let positions: Vec<[f32; 3]> = solver.clothe.vertices.iter().map(|v| xyz(&v.position)).collect();
+        let box_distances: Vec<f32> = positions.iter().map(|&p| b.sdf(p)).collect();
+        let capsule_distances: Vec<f32> = positions.iter().map(|&p| capsule.sdf(p)).collect();
+
+        for distances in [box_distances, capsule_distances] {
+            assert!(distances.iter().any(|&distance| distance < 1e-3), "the clothe didn't reach the collider");
+            assert!(distances.iter().all(|&distance| distance >= -1e-4), "distances {:?}", distances);
+        }
+    }
+
+    #[test]
+    fn self_collision_pushes_unlinked_vertices_to_the_thickness() {
+        let data = SelfCollisionData { thickness: 0.01, table_size: 0, bucket_size: 0, nb_vertices: 2 };
+        let pair = || {
+            let mut vertices = [Node::zeroed(); 2];
+            vertices[1].position[0] = 0.004;
+            vertices
+        };
+
+        let mut vertices = pair();
+        self_collision(&mut vertices, &[0, 0, 0], &[], &data);
+        let distance = vertices[1].position[0] - vertices[0].position[0];
+        assert!((distance - data.thickness).abs() < 1e-6, "distance {}", distance);
+
+        // Vertices linked by a spring are left alone
+        let springs = [
+            Spring::new(1, 0.004, SpringKind::Structural, SpringDirection::Weft),
+            Spring::new(0, 0.004, SpringKind::Structural, SpringDirection::Weft),
+        ];
+        let mut vertices = pair();
+        self_collision(&mut vertices, &[0, 1, 2], &springs, &data);
+        assert_eq!(vertices[1].position[0] - vertices[0].position[0], 0.004);
+    }
+
+    #[test]
+    fn wind_pushes_the_clothe_downwind() {
+        let mut scene = scene();
+        scene.simulation.gravity = 0.0;
+        // The clothe is horizontal, the wind has to cross it to push it
+        scene.wind = Some(WindConfig { velocity: [0.0, -2.0, 5.0], ..WindConfig::default() });
+        let solver = run(&scene, 50);
+
+        let vertices = &solver.clothe.vertices;
+        let mean_z = vertices.iter().map(|v| v.velocity[2]).sum::<f32>() / vertices.len() as f32;
+        assert!(mean_z > 0.0, "mean z velocity {}", mean_z);
+    }
+
+    #[test]
+    fn every_integrator_falls_at_g_t() {
+        for integrator in [Integrator::SymplecticEuler, Integrator::Verlet, Integrator::Rk4] {
+            let mut scene = scene();
+            scene.simulation.integrator = integrator;
+            scene.simulation.damping_factor = 0.0;
+            let solver = run(&scene, 100);
+
+            // Verlet derives the velocity from two close f32 positions, hence the margin
+            let expected = scene.simulation.gravity * scene.time_step() * 100.0;
+            for vertex in &solver.clothe.vertices {
+                assert!((vertex.velocity[1] - expected).abs() < 2e-2 * expected.abs(),
+                    "{:?}: velocity {:?}, expected {}", integrator, vertex.velocity, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn rigid_xpbd_clothe_hangs_without_stretching() {
+        let mut scene = scene();
+        scene.clothe.pins = PinSpec::TopCorners;
+        scene.simulation.solver = Solver::Xpbd;
+        scene.simulation.compliance = 0.0;
+        let solver = run(&scene, 600);
+
+        let Clothe { vertices, spring_offsets, springs, .. } = &solver.clothe;
+        let mut max_strain: f32 = 0.0;
+        for index in 0..vertices.len() {
+            for spring in &springs[spring_range(spring_offsets, index)] {
+                if spring.kind == SpringKind::Structural.index() {
+                    let offset = sub(xyz(&vertices[index].position), xyz(&vertices[spring.link as usize].position));
+                    max_strain = max_strain.max(length(offset) / spring.rest_distance - 1.0);
+                }
+            }
+        }
+        assert!(max_strain < 1e-2, "max strain {}", max_strain);
+    }
+
+    #[test]
+    fn displaced_vertex_tears_off_its_triangles() {
+        let mut scene = scene();
+        scene.material.tear_strain = 0.5;
+        let mut clothe = scene.build_clothe().unwrap();
+        let clothe_data = scene.clothe_data(&clothe);
+
+        // Vertex in the middle of the clothe, pulled far above it
+        let torn = clothe.vertices.len() / 2;
+        clothe.vertices[torn].position[1] += 1.0;
+        compute_distances(&clothe.vertices, &clothe.spring_offsets, &mut clothe.springs, &clothe_data);
+
+        let Clothe { spring_offsets, springs, indices, .. } = &clothe;
+        assert!(springs[spring_range(spring_offsets, torn)].iter().all(|spring| spring.link as usize == torn));
+        for index in (0..clothe.vertices.len()).filter(|&index| index != torn) {
+            assert!(springs[spring_range(spring_offsets, index)].iter().all(|spring| spring.link as usize != torn));
+        }
+
+        let masked = mask_torn_triangles(indices, spring_offsets, springs);
+        for (triangle, masked) in indices.chunks_exact(3).zip(masked.chunks_exact(3)) {
+            if triangle.contains(&(torn as u32)) {
+                assert!(masked[0] == masked[1] && masked[1] == masked[2], "{:?} is not degenerate", masked);
+            } else {
+                assert_eq!(triangle, masked);
+            }
+        }
+    }
+
+    #[test]
+    fn torn_triangles_dont_bend_the_normals() {
+        let mut scene = scene();
+        scene.material.tear_strain = 0.5;
+        let mut clothe = scene.build_clothe().unwrap();
+        let clothe_data = scene.clothe_data(&clothe);
+        let triangles = unique_triangles(&clothe.indices);
+
+        let torn = clothe.vertices.len() / 2;
+        clothe.vertices[torn].position[1] += 1.0;
+        compute_distances(&clothe.vertices, &clothe.spring_offsets, &mut clothe.springs, &clothe_data);
+        compute_normals(&mut clothe.vertices, &triangles, &clothe.spring_offsets, &clothe.springs, &clothe_data);
+
+        // The rest of the clothe is still flat
+        for (_, vertex) in clothe.vertices.iter().enumerate().filter(|&(index, _)| index != torn) {
+            assert!(vertex.normal[0].abs() < 1e-6 && vertex.normal[2].abs() < 1e-6, "normal {:?}", vertex.normal);
+        }
+    }
+
+    #[test]
+    fn only_a_rough_moving_sphere_drags_the_clothe() {
+        // Vertex at rest just inside the top of a sphere sliding along x
+        let drag = |friction_factor| {
+            let (mut position, mut velocity) = ([0.0, 0.999, 0.0], [0.0; 3]);
+            project_moving(&mut position, &mut velocity, [0.0, 1.0, 0.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0],
+                friction_factor, 0.01);
+            velocity[0]
+        };
+
+        assert_eq!(drag(0.0), 0.0);
+        assert!(drag(0.05) > 0.0 && drag(0.05) < 1.0, "drag {}", drag(0.05));
+        assert_eq!(drag(20.0), 1.0);
+    }
+
+    #[test]
+    fn still_sphere_drops_the_tangential_velocity() {
+        for friction_factor in [0.0, 0.1, 20.0] {
+            let (mut position, mut velocity) = ([0.0, 0.999, 0.0], [0.5, -0.1, 0.0]);
+            let (mut expected_position, mut expected_velocity) = (position, velocity);
+            project(&mut expected_position, &mut expected_velocity, [0.0, 1.0, 0.0], 0.01);
+            project_moving(&mut position, &mut velocity, [0.0, 1.0, 0.0], [0.0, 1.0, 0.0], [0.0; 3],
+                friction_factor, 0.01);
+
+            assert_eq!((position, velocity), (expected_position, expected_velocity));
+        }
+    }
+
+    #[test]
+    fn default_scene_settles_on_the_sphere() {
+        // Default values on a coarser clothe, for four seconds
+        let mut scene = Scene::default();
+        scene.clothe.number_squares = 12;
+        let solver = run(&scene, 240 * scene.simulation.iterations);
+
+        for vertex in &solver.clothe.vertices {
+            assert!(vertex.position.iter().all(|x| x.is_finite()), "position {:?}", vertex.position);
+            assert!(length(xyz(&vertex.velocity)) < 0.5, "velocity {:?}", vertex.velocity);
+            assert!(vertex.position[1] > -0.5, "the clothe slid off the sphere: {:?}", vertex.position);
+        }
+    }
+}
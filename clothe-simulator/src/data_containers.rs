@@ -6,6 +6,55 @@ pub struct Sphere {
     pub z: f32,
     pub radius: f32,
     pub friction_factor: f32,
+    // Kinematic spheres move at this velocity during the substeps of a frame
+    pub velocity_x: f32,
+    pub velocity_y: f32,
+    pub velocity_z: f32,
+}
+
+// Box centered on (x, y, z) with its half sizes along its local axes,
+// oriented by the unit quaternion `rotation`
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct OrientedBox {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub half_x: f32,
+    pub half_y: f32,
+    pub half_z: f32,
+    pub rotation_x: f32,
+    pub rotation_y: f32,
+    pub rotation_z: f32,
+    pub rotation_w: f32,
+    pub friction_factor: f32,
+}
+
+// Points closer than `radius` from the segment between start and end
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Capsule {
+    pub start_x: f32,
+    pub start_y: f32,
+    pub start_z: f32,
+    pub end_x: f32,
+    pub end_y: f32,
+    pub end_z: f32,
+    pub radius: f32,
+    pub friction_factor: f32,
+}
+
+// Infinite plane of points `p` where `dot(normal, p) == offset`, ignored
+// when `enabled` is 0
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Plane {
+    pub normal_x: f32,
+    pub normal_y: f32,
+    pub normal_z: f32,
+    pub offset: f32,
+    pub friction_factor: f32,
+    pub enabled: u32,
 }
 
 #[repr(C)]
@@ -15,7 +64,7 @@ pub struct ClotheData {
     pub center_y: f32,
     pub center_z: f32,
     pub nb_vertices: u32,
-    pub mass: f32,
+    pub tear_strain: f32,
 }
 
 #[repr(C)]
@@ -25,4 +74,75 @@ pub struct ComputeData {
     pub damping_factor: f32,
     pub gravity: f32,
     pub delta_time: f32,
+    pub nb_spheres: u32,
+    pub nb_boxes: u32,
+    pub nb_capsules: u32,
+    pub compliance: f32,
+    // Factors of `spring_contant` and `damping_factor` for each `SpringKind`
+    pub structural_stiffness: f32,
+    pub shear_stiffness: f32,
+    pub bend_stiffness: f32,
+    pub structural_damping: f32,
+    pub shear_damping: f32,
+    pub bend_damping: f32,
+    // Factors of the stiffness for each `SpringDirection`, the bend springs
+    // use the bend factors and the others the stretch factors
+    pub warp_stretch: f32,
+    pub weft_stretch: f32,
+    pub bias_stretch: f32,
+    pub warp_bend: f32,
+    pub weft_bend: f32,
+    pub bias_bend: f32,
+}
+
+// Vertex dragged toward a target with the mouse, see `VertexGrab`
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GrabData {
+    pub target_x: f32,
+    pub target_y: f32,
+    pub target_z: f32,
+    pub vertex: u32,
+    pub grabbed: u32,
+    pub max_speed: f32,
+}
+
+// Blend of the last two substeps drawn, see `RenderInterpolation`
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct InterpolationData {
+    pub alpha: f32,
+    pub nb_vertices: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SelfCollisionData {
+    pub thickness: f32,
+    pub table_size: u32,
+    pub bucket_size: u32,
+    pub nb_vertices: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct WindData {
+    pub velocity_x: f32,
+    pub velocity_y: f32,
+    pub velocity_z: f32,
+    pub turbulence: f32,
+    pub drag_coefficient: f32,
+    pub lift_coefficient: f32,
+    pub air_density: f32,
+    pub time: f32,
+    pub nb_triangles: u32,
+}
+
+// Every collider of the scene
+#[derive(Clone, Debug)]
+pub struct Colliders {
+    pub spheres: Vec<Sphere>,
+    pub boxes: Vec<OrientedBox>,
+    pub capsules: Vec<Capsule>,
+    pub plane: Plane,
 }
\ No newline at end of file
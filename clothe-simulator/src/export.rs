@@ -0,0 +1,247 @@
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::node::Node;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshFormat {
+    Obj,
+    Ply,
+}
+
+impl MeshFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            MeshFormat::Obj => "obj",
+            MeshFormat::Ply => "ply",
+        }
+    }
+}
+
+impl FromStr for MeshFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "obj" => Ok(MeshFormat::Obj),
+            "ply" => Ok(MeshFormat::Ply),
+            _ => Err(format!("unknown mesh format `{}`, expected `obj` or `ply`", s)),
+        }
+    }
+}
+
+/// The clothe indices draw every triangle twice, once per side. Keep only the
+/// first winding of each triangle for the exported mesh.
+pub fn unique_triangles(indices: &[u32]) -> Vec<[u32; 3]> {
+    let mut seen = HashSet::new();
+
+    indices
+        .chunks_exact(3)
+        .map(|t| [t[0], t[1], t[2]])
+        // Torn triangles are degenerate
+        .filter(|t| t[0] != t[1] && t[1] != t[2] && t[2] != t[0])
+        .filter(|t| {
+            let mut key = *t;
+            key.sort_unstable();
+            seen.insert(key)
+        })
+        .collect()
+}
+
+pub fn write_obj<W: Write>(writer: &mut W, vertices: &[Node], triangles: &[[u32; 3]]) -> io::Result<()> {
+    writeln!(writer, "# Clothe simulator export")?;
+
+    for vertex in vertices {
+        let [x, y, z, _] = vertex.position;
+        writeln!(writer, "v {} {} {}", x, y, z)?;
+    }
+    // OBJ texture coordinates start at the bottom of the image
+    for vertex in vertices {
+        let [u, v, _, _] = vertex.tex_coords;
+        writeln!(writer, "vt {} {}", u, 1.0 - v)?;
+    }
+    for vertex in vertices {
+        let [x, y, z, _] = vertex.normal;
+        writeln!(writer, "vn {} {} {}", x, y, z)?;
+    }
+
+    // OBJ indices start at 1
+    for triangle in triangles {
+        let [a, b, c] = triangle.map(|i| i + 1);
+        writeln!(writer, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}")?;
+    }
+
+    Ok(())
+}
+
+pub fn write_ply<W: Write>(writer: &mut W, vertices: &[Node], triangles: &[[u32; 3]]) -> io::Result<()> {
+    write!(
+        writer,
+        "ply\n\
+         format binary_little_endian 1.0\n\
+         comment Clothe simulator export\n\
+         element vertex {}\n\
+         property float x\nproperty float y\nproperty float z\n\
+         property float nx\nproperty float ny\nproperty float nz\n\
+         property float s\nproperty float t\n\
+         element face {}\n\
+         property list uchar uint vertex_indices\n\
+         end_header\n",
+        vertices.len(),
+        triangles.len()
+    )?;
+
+    for vertex in vertices {
+        let values = [
+            vertex.position[0],
+            vertex.position[1],
+            vertex.position[2],
+            vertex.normal[0],
+            vertex.normal[1],
+            vertex.normal[2],
+            vertex.tex_coords[0],
+            vertex.tex_coords[1],
+        ];
+        for value in values {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+    }
+
+    for triangle in triangles {
+        writer.write_all(&[3u8])?;
+        for index in triangle {
+            writer.write_all(&index.to_le_bytes())?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn write_mesh<P: AsRef<Path>>(
+    path: P,
+    format: MeshFormat,
+    vertices: &[Node],
+    triangles: &[[u32; 3]],
+) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    match format {
+        MeshFormat::Obj => write_obj(&mut writer, vertices, triangles)?,
+        MeshFormat::Ply => write_ply(&mut writer, vertices, triangles)?,
+    }
+
+    writer.flush()
+}
+
+/// Write the clothe every `every` frames as `clothe_00000.<ext>`,
+/// `clothe_00001.<ext>`, ...
+pub struct MeshExporter {
+    pub directory: PathBuf,
+    pub format: MeshFormat,
+    pub every: u32,
+    frame: u32,
+    exported: u32,
+}
+
+impl MeshExporter {
+    pub fn new<P: Into<PathBuf>>(directory: P, format: MeshFormat, every: u32) -> io::Result<Self> {
+        let directory = directory.into();
+        std::fs::create_dir_all(&directory)?;
+
+        Ok(Self {
+            directory,
+            format,
+            every: every.max(1),
+            frame: 0,
+            exported: 0,
+        })
+    }
+
+    /// Count a new frame, return `true` if it must be exported.
+    pub fn next_frame(&mut self) -> bool {
+        let due = self.frame % self.every == 0;
+        self.frame += 1;
+        due
+    }
+
+    pub fn export(&mut self, vertices: &[Node], triangles: &[[u32; 3]]) -> io::Result<PathBuf> {
+        let path = self
+            .directory
+            .join(format!("clothe_{:05}.{}", self.exported, self.format.extension()));
+
+        write_mesh(&path, self.format, vertices, triangles)?;
+        self.exported += 1;
+
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytemuck::Zeroable;
+
+    use super::*;
+
+    // One square, drawn on both sides as the clothe indices
+    fn square() -> (Vec<Node>, Vec<u32>) {
+        let vertices = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]]
+            .map(|[x, z]| Node {
+                position: [x, 0.0, z, 1.0],
+                normal: [0.0, 1.0, 0.0, 0.0],
+                tex_coords: [x, z, 0.0, 0.0],
+                ..Node::zeroed()
+            })
+            .to_vec();
+        (vertices, vec![0, 1, 2, 0, 2, 3, 2, 1, 0, 3, 2, 0])
+    }
+
+    #[test]
+    fn each_triangle_is_kept_once() {
+        let (_, mut indices) = square();
+        // Torn triangle
+        indices.extend([1, 1, 1]);
+
+        assert_eq!(unique_triangles(&indices), vec![[0, 1, 2], [0, 2, 3]]);
+    }
+
+    #[test]
+    fn obj_indices_start_at_1() {
+        let (vertices, indices) = square();
+        let mut output = Vec::new();
+        write_obj(&mut output, &vertices, &unique_triangles(&indices)).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines.len(), 1 + 3 * 4 + 2);
+        assert_eq!(lines[3], "v 1 0 1");
+        assert_eq!(lines[5], "vt 0 1");
+        assert_eq!(lines[11], "vn 0 1 0");
+        assert_eq!(&lines[13..], ["f 1/1/1 2/2/2 3/3/3", "f 1/1/1 3/3/3 4/4/4"]);
+    }
+
+    #[test]
+    fn ply_payload_is_binary_little_endian() {
+        let (vertices, indices) = square();
+        let triangles = unique_triangles(&indices);
+        let mut output = Vec::new();
+        write_ply(&mut output, &vertices, &triangles).unwrap();
+
+        let end = b"end_header\n";
+        let header_size = output.windows(end.len()).position(|window| window == end).unwrap() + end.len();
+        let header = String::from_utf8(output[..header_size].to_vec()).unwrap();
+        assert!(header.starts_with("ply\nformat binary_little_endian 1.0\n"));
+        assert!(header.contains("element vertex 4\n") && header.contains("element face 2\n"));
+
+        // 8 floats per vertex, a count and 3 indices per face
+        let payload = &output[header_size..];
+        assert_eq!(payload.len(), 4 * 8 * 4 + 2 * (1 + 3 * 4));
+        assert_eq!(payload[..4], 0.0f32.to_le_bytes());
+        assert_eq!(payload[4 * 8..4 * 9], 1.0f32.to_le_bytes());
+        assert_eq!(payload[4 * 8 * 4..][..5], [3, 0, 0, 0, 0]);
+    }
+}
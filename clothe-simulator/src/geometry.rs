@@ -0,0 +1,180 @@
+use wgpu_bootstrap::{default::Vertex, wgpu};
+
+use crate::{
+    data_containers::{Capsule, OrientedBox, Plane},
+    vector::{add, cross, normalize, quat_rotate, scale, sub},
+};
+
+/// Placement of a mesh drawn with the sphere pipeline, its vertices are
+/// scaled then moved by `position`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct MeshInstance {
+    pub position: [f32; 3],
+    pub scale: f32,
+}
+
+impl MeshInstance {
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<MeshInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float32,
+                },
+            ],
+        }
+    }
+}
+
+// Square of side `size` lying on the plane, centered on the point of the
+// plane closest to the origin. Only the side facing the normal is drawn.
+pub fn plane_mesh(plane: &Plane, size: f32) -> (Vec<Vertex>, Vec<u32>) {
+    let normal = [plane.normal_x, plane.normal_y, plane.normal_z];
+    let center = normal.map(|n| n * plane.offset);
+
+    // Any axis not parallel to the normal gives the plane basis
+    let axis = if plane.normal_y.abs() < 0.9 { [0.0, 1.0, 0.0] } else { [1.0, 0.0, 0.0] };
+    let tangent = normalize(cross(axis, normal));
+    let bitangent = cross(normal, tangent);
+    let half = size / 2.0;
+
+    let vertices = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+        .iter()
+        .map(|&(u, v): &(f32, f32)| Vertex {
+            position: [0, 1, 2].map(|i| center[i] + (tangent[i] * u + bitangent[i] * v) * half),
+            normal,
+            tangent,
+            tex_coords: [(u + 1.0) / 2.0, (v + 1.0) / 2.0],
+        })
+        .collect();
+
+    (vertices, vec![0, 1, 2, 0, 2, 3])
+}
+
+// Box with one quad per face so every face has its own normal
+pub fn box_mesh(b: &OrientedBox) -> (Vec<Vertex>, Vec<u32>) {
+    let center = b.center();
+    let half = b.half_extents();
+    let rotation = b.rotation();
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    for axis in 0..3 {
+        for side in [-1.0, 1.0] {
+            // Local basis of the face, `u x v` points outside
+            let mut normal = [0.0; 3];
+            normal[axis] = side;
+            let mut u = [0.0; 3];
+            u[(axis + 1) % 3] = 1.0;
+            let mut v = [0.0; 3];
+            v[(axis + 2) % 3] = side;
+
+            let start = vertices.len() as u32;
+            for (s, t) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+                let local = [0, 1, 2].map(|i| (normal[i] + u[i] * s + v[i] * t) * half[i]);
+
+                vertices.push(Vertex {
+                    position: add(center, quat_rotate(rotation, local)),
+                    normal: quat_rotate(rotation, normal),
+                    tangent: quat_rotate(rotation, u),
+                    tex_coords: [(s + 1.0) / 2.0, (t + 1.0) / 2.0],
+                });
+            }
+            indices.extend_from_slice(&[start, start + 1, start + 2, start, start + 2, start + 3]);
+        }
+    }
+
+    (vertices, indices)
+}
+
+// Cylinder between the two ends closed by two half spheres, `segments`
+// vertices around the axis
+pub fn capsule_mesh(capsule: &Capsule, segments: u32) -> (Vec<Vertex>, Vec<u32>) {
+    let start = capsule.start();
+    let end = capsule.end();
+    let direction = sub(end, start);
+    let axis = if direction == [0.0; 3] { [0.0, 1.0, 0.0] } else { normalize(direction) };
+    let other = if axis[1].abs() < 0.9 { [0.0, 1.0, 0.0] } else { [1.0, 0.0, 0.0] };
+    let tangent = normalize(cross(other, axis));
+    let bitangent = cross(axis, tangent);
+
+    // Rings from the bottom pole of the start cap to the top pole of the end cap
+    let cap_rings = segments / 4;
+    let mut rings = Vec::new();
+    for ring in 0..=cap_rings {
+        let angle = -std::f32::consts::FRAC_PI_2 * (1.0 - ring as f32 / cap_rings as f32);
+        rings.push((start, angle));
+    }
+    for ring in 0..=cap_rings {
+        let angle = std::f32::consts::FRAC_PI_2 * ring as f32 / cap_rings as f32;
+        rings.push((end, angle));
+    }
+
+    let mut vertices = Vec::new();
+    for (ring, &(ring_center, latitude)) in rings.iter().enumerate() {
+        for segment in 0..=segments {
+            let longitude = 2.0 * std::f32::consts::PI * segment as f32 / segments as f32;
+            let around = add(scale(tangent, longitude.cos()), scale(bitangent, longitude.sin()));
+            let normal = add(scale(around, latitude.cos()), scale(axis, latitude.sin()));
+
+            vertices.push(Vertex {
+                position: add(ring_center, scale(normal, capsule.radius)),
+                normal,
+                tangent: cross(axis, around),
+                tex_coords: [segment as f32 / segments as f32, ring as f32 / (rings.len() - 1) as f32],
+            });
+        }
+    }
+
+    let row = segments + 1;
+    let mut indices = Vec::new();
+    for ring in 0..(rings.len() as u32 - 1) {
+        for segment in 0..segments {
+            let current = ring * row + segment;
+            let next = current + row;
+            indices.extend_from_slice(&[current, current + 1, next + 1, current, next + 1, next]);
+        }
+    }
+
+    (vertices, indices)
+}
+
+// Concatenate meshes into one vertex and index list
+pub fn merge_meshes(meshes: Vec<(Vec<Vertex>, Vec<u32>)>) -> (Vec<Vertex>, Vec<u32>) {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    for (mesh_vertices, mesh_indices) in meshes {
+        let offset = vertices.len() as u32;
+        indices.extend(mesh_indices.iter().map(|i| i + offset));
+        vertices.extend(mesh_vertices);
+    }
+
+    (vertices, indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use bytemuck::Zeroable;
+
+    use super::*;
+
+    #[test]
+    fn merged_indices_go_past_the_u16_range() {
+        let b = OrientedBox { half_x: 1.0, half_y: 1.0, half_z: 1.0, rotation_w: 1.0, ..OrientedBox::zeroed() };
+        let (vertices, indices) = merge_meshes(vec![box_mesh(&b); 3000]);
+
+        assert_eq!(vertices.len(), 3000 * 24);
+        assert_eq!(indices.len(), 3000 * 36);
+        assert_eq!(*indices.iter().max().unwrap() as usize, vertices.len() - 1);
+    }
+}
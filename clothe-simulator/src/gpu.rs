@@ -0,0 +1,268 @@
+use wgpu_bootstrap::{context::Context, texture::create_texture_bind_group, wgpu};
+
+/// What the simulation and its rendering use from the GPU. The window context
+/// of wgpu-bootstrap provides it, and so does `HeadlessContext` which has no
+/// window nor surface.
+pub trait GpuContext {
+    fn device(&self) -> &wgpu::Device;
+    fn queue(&self) -> &wgpu::Queue;
+    fn texture_bind_group_layout(&self) -> &wgpu::BindGroupLayout;
+    fn camera_bind_group_layout(&self) -> &wgpu::BindGroupLayout;
+    fn get_aspect_ratio(&self) -> f32;
+    fn create_buffer<T: bytemuck::Pod>(&self, data: &[T], usage: wgpu::BufferUsages) -> wgpu::Buffer;
+    fn update_buffer<T: bytemuck::Pod>(&self, buffer: &wgpu::Buffer, data: &[T]);
+    fn create_bind_group(
+        &self,
+        label: &str,
+        layout: &wgpu::BindGroupLayout,
+        entries: &[wgpu::BindGroupEntry],
+    ) -> wgpu::BindGroup;
+    fn create_render_pipeline(
+        &self,
+        label: &str,
+        source: &str,
+        vertex_buffer_layouts: &[wgpu::VertexBufferLayout],
+        bind_group_layouts: &[&wgpu::BindGroupLayout],
+        topology: wgpu::PrimitiveTopology,
+    ) -> wgpu::RenderPipeline;
+    // Bind group of a sRGB image and its sampler, for `texture_bind_group_layout`
+    fn create_texture_bind_group(&self, label: &str, bytes: &[u8]) -> wgpu::BindGroup;
+}
+
+impl GpuContext for Context {
+    fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    fn texture_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.texture_bind_group_layout
+    }
+
+    fn camera_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.camera_bind_group_layout
+    }
+
+    fn get_aspect_ratio(&self) -> f32 {
+        Context::get_aspect_ratio(self)
+    }
+
+    fn create_buffer<T: bytemuck::Pod>(&self, data: &[T], usage: wgpu::BufferUsages) -> wgpu::Buffer {
+        Context::create_buffer(self, data, usage)
+    }
+
+    fn update_buffer<T: bytemuck::Pod>(&self, buffer: &wgpu::Buffer, data: &[T]) {
+        Context::update_buffer(self, buffer, data)
+    }
+
+    fn create_bind_group(
+        &self,
+        label: &str,
+        layout: &wgpu::BindGroupLayout,
+        entries: &[wgpu::BindGroupEntry],
+    ) -> wgpu::BindGroup {
+        Context::create_bind_group(self, label, layout, entries)
+    }
+
+    fn create_render_pipeline(
+        &self,
+        label: &str,
+        source: &str,
+        vertex_buffer_layouts: &[wgpu::VertexBufferLayout],
+        bind_group_layouts: &[&wgpu::BindGroupLayout],
+        topology: wgpu::PrimitiveTopology,
+    ) -> wgpu::RenderPipeline {
+        Context::create_render_pipeline(self, label, source, vertex_buffer_layouts, bind_group_layouts, topology)
+    }
+
+    fn create_texture_bind_group(&self, label: &str, bytes: &[u8]) -> wgpu::BindGroup {
+        create_texture_bind_group(self, &self.create_srgb_texture(label, bytes))
+    }
+}
+
+// Must match the workgroup size of the compute shaders
+pub const WORKER_SIZE: u32 = 255;
+
+// Function to calcul the number of workers to launch
+pub fn get_workers(nb: u32) -> u32 {
+    nb.div_ceil(WORKER_SIZE)
+}
+
+// Storage buffers can't be empty, keep a placeholder when there is no data
+pub fn create_storage_buffer<T: bytemuck::Pod>(context: &impl GpuContext, data: &[T], usage: wgpu::BufferUsages)
+    -> wgpu::Buffer {
+    if data.is_empty() {
+        context.create_buffer(&[T::zeroed()], wgpu::BufferUsages::STORAGE | usage)
+    } else {
+        context.create_buffer(data, wgpu::BufferUsages::STORAGE | usage)
+    }
+}
+
+// Compute pipeline for a given entry point of the shader, the layout is
+// deduced from the bindings used by this entry point
+pub fn create_compute_pipeline(context: &impl GpuContext, label: &str, source: &str, entry_point: &str)
+    -> wgpu::ComputePipeline {
+    let module = context.device().create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+
+    context.device().create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some(label),
+        layout: None,
+        module: &module,
+        entry_point,
+    })
+}
+
+// Bind group where every entry is a whole buffer
+pub fn create_buffer_bind_group(
+    context: &impl GpuContext,
+    label: &str,
+    layout: &wgpu::BindGroupLayout,
+    buffers: &[(u32, &wgpu::Buffer)],
+) -> wgpu::BindGroup {
+    let entries: Vec<wgpu::BindGroupEntry> = buffers
+        .iter()
+        .map(|&(binding, buffer)| wgpu::BindGroupEntry {
+            binding,
+            resource: buffer.as_entire_binding(),
+        })
+        .collect();
+
+    context.create_bind_group(label, layout, &entries)
+}
+
+/// One entry point of a multi pass shader with its two bind groups, group 0
+/// for the storage buffers and group 1 for the uniforms.
+pub struct ComputeStage {
+    pipeline: wgpu::ComputePipeline,
+    bind_groups: [wgpu::BindGroup; 2],
+    workers: u32,
+}
+
+impl ComputeStage {
+    pub fn new(
+        context: &impl GpuContext,
+        label: &str,
+        source: &str,
+        entry_point: &str,
+        buffers: [&[(u32, &wgpu::Buffer)]; 2],
+        nb_invocations: u32,
+    ) -> Self {
+        let label = format!("{} {}", label, entry_point);
+        let pipeline = create_compute_pipeline(context, &label, source, entry_point);
+        let bind_groups = [0, 1].map(|group| {
+            create_buffer_bind_group(context, &label, &pipeline.get_bind_group_layout(group),
+                buffers[group as usize])
+        });
+
+        Self {
+            pipeline,
+            bind_groups,
+            workers: get_workers(nb_invocations),
+        }
+    }
+
+    pub fn dispatch<'a>(&'a self, compute_pass: &mut wgpu::ComputePass<'a>) {
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &self.bind_groups[0], &[]);
+        compute_pass.set_bind_group(1, &self.bind_groups[1], &[]);
+        compute_pass.dispatch_workgroups(self.workers, 1, 1);
+    }
+}
+
+pub fn storage_layout_entry(binding: u32, read_only: bool) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+pub fn uniform_layout_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+/// Compute pipelines dispatched in sequence for each substep. They share
+/// explicit bind group layouts, so the same bind groups work with every entry
+/// point of the shader whatever bindings it uses. An entry point repeated in
+/// the sequence has a single pipeline.
+pub struct PipelineSequence {
+    pipelines: Vec<wgpu::ComputePipeline>,
+    sequence: Vec<usize>,
+}
+
+impl PipelineSequence {
+    pub fn new(
+        context: &impl GpuContext,
+        label: &str,
+        source: &str,
+        entry_points: &[&str],
+        bind_group_layouts: &[&wgpu::BindGroupLayout],
+    ) -> Self {
+        let module = context.device().create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(label),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+        let layout = context.device().create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(label),
+            bind_group_layouts,
+            push_constant_ranges: &[],
+        });
+
+        let mut names: Vec<&str> = Vec::new();
+        let mut pipelines = Vec::new();
+        let sequence = entry_points
+            .iter()
+            .map(|&entry_point| {
+                if let Some(index) = names.iter().position(|&name| name == entry_point) {
+                    return index;
+                }
+
+                pipelines.push(context.device().create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: Some(&format!("{} {}", label, entry_point)),
+                    layout: Some(&layout),
+                    module: &module,
+                    entry_point,
+                }));
+                names.push(entry_point);
+                names.len() - 1
+            })
+            .collect();
+
+        Self { pipelines, sequence }
+    }
+
+    pub fn dispatch<'a>(
+        &'a self,
+        compute_pass: &mut wgpu::ComputePass<'a>,
+        bind_groups: &[&'a wgpu::BindGroup],
+        workers: u32,
+    ) {
+        for &index in &self.sequence {
+            compute_pass.set_pipeline(&self.pipelines[index]);
+            for (group, bind_group) in bind_groups.iter().enumerate() {
+                compute_pass.set_bind_group(group as u32, bind_group, &[]);
+            }
+            compute_pass.dispatch_workgroups(workers, 1, 1);
+        }
+    }
+}
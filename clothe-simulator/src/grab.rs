@@ -0,0 +1,107 @@
+use wgpu_bootstrap::{
+    cgmath::{InnerSpace, Point3},
+    wgpu,
+};
+
+use crate::{camera::Ray, data_containers::GrabData, gpu::{ComputeStage, GpuContext}, node::Node};
+
+// Largest angle between the cursor ray and a vertex for it to be picked
+const PICK_ANGLE: f32 = 3.0 * std::f32::consts::PI / 180.0;
+// Fastest speed of the grabbed vertex, in m/s
+const MAX_SPEED: f32 = 5.0;
+
+/// Free vertex closest to the ray within `PICK_ANGLE`, with its distance along
+/// the ray. Pinned vertices can't be grabbed.
+pub fn pick_vertex(vertices: &[Node], ray: &Ray) -> Option<(u32, f32)> {
+    let min_cosine = PICK_ANGLE.cos();
+
+    vertices
+        .iter()
+        .enumerate()
+        .filter(|(_, vertex)| vertex.pinned == 0)
+        .filter_map(|(index, vertex)| {
+            let [x, y, z, _] = vertex.position;
+            let offset = Point3::new(x, y, z) - ray.origin;
+            let distance = offset.magnitude();
+            if distance <= 0.0 {
+                return None;
+            }
+
+            let cosine = offset.dot(ray.direction) / distance;
+            (cosine >= min_cosine).then_some((index as u32, cosine, offset.dot(ray.direction)))
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(index, _, depth)| (index, depth))
+}
+
+/// Vertex of the clothe held with the mouse. It is picked on the CPU from a
+/// readback of the vertices, then a compute pass moves it toward the point
+/// under the cursor after every substep.
+pub struct VertexGrab {
+    data: GrabData,
+    data_buffer: wgpu::Buffer,
+    stage: ComputeStage,
+    // Distance of the grabbed vertex along the cursor ray, kept while dragging
+    depth: f32,
+}
+
+impl VertexGrab {
+    pub fn new(context: &impl GpuContext, vertex_buffer: &wgpu::Buffer, compute_data_buffer: &wgpu::Buffer) -> Self {
+        let data = GrabData {
+            target_x: 0.0,
+            target_y: 0.0,
+            target_z: 0.0,
+            vertex: 0,
+            grabbed: 0,
+            max_speed: MAX_SPEED,
+        };
+        let data_buffer = context.create_buffer(&[data], wgpu::BufferUsages::UNIFORM);
+        let stage = ComputeStage::new(context, "Grab", include_str!("shaders/grab.wgsl"), "main",
+            [&[(0, vertex_buffer)], &[(0, &data_buffer), (1, compute_data_buffer)]], 1);
+
+        Self {
+            data,
+            data_buffer,
+            stage,
+            depth: 0.0,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.data.grabbed != 0
+    }
+
+    /// Grabs the vertex under the cursor, returns whether there was one.
+    pub fn grab(&mut self, context: &impl GpuContext, vertices: &[Node], ray: &Ray) -> bool {
+        let Some((vertex, depth)) = pick_vertex(vertices, ray) else {
+            return false;
+        };
+
+        self.data.vertex = vertex;
+        self.data.grabbed = 1;
+        self.depth = depth;
+        self.drag(context, ray);
+
+        true
+    }
+
+    /// Moves the target of the grabbed vertex on the new cursor ray.
+    pub fn drag(&mut self, context: &impl GpuContext, ray: &Ray) {
+        let target = ray.at(self.depth);
+        self.data.target_x = target.x;
+        self.data.target_y = target.y;
+        self.data.target_z = target.z;
+        context.update_buffer(&self.data_buffer, &[self.data]);
+    }
+
+    pub fn release(&mut self, context: &impl GpuContext) {
+        self.data.grabbed = 0;
+        context.update_buffer(&self.data_buffer, &[self.data]);
+    }
+
+    pub fn dispatch<'a>(&'a self, compute_pass: &mut wgpu::ComputePass<'a>) {
+        if self.is_active() {
+            self.stage.dispatch(compute_pass);
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use std::{fmt, fs, io, path::Path};
+
+/// Triangle mesh read from an OBJ file. Every position is one vertex of the
+/// clothe, so the faces stay connected across the UV seams: a position keeps
+/// the first texture coordinates it is used with.
+#[derive(Debug, Clone, Default)]
+pub struct ObjMesh {
+    pub positions: Vec<[f32; 3]>,
+    pub tex_coords: Vec<[f32; 2]>,
+    pub triangles: Vec<[u32; 3]>,
+}
+
+#[derive(Debug)]
+pub enum ImportError {
+    Io(String, io::Error),
+    Parse(usize, String),
+    Empty,
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io(path, error) => write!(f, "cannot read mesh file `{}`: {}", path, error),
+            ImportError::Parse(line, message) => write!(f, "invalid OBJ file, line {}: {}", line, message),
+            ImportError::Empty => write!(f, "the OBJ file has no face"),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+pub fn read_obj<P: AsRef<Path>>(path: P) -> Result<ObjMesh, ImportError> {
+    let name = path.as_ref().display().to_string();
+    let source = fs::read_to_string(&path).map_err(|e| ImportError::Io(name, e))?;
+
+    parse_obj(&source)
+}
+
+// OBJ indices start at 1, negative indices count from the last element
+fn resolve_index(token: &str, count: usize, line: usize) -> Result<usize, ImportError> {
+    let index: i64 = token
+        .parse()
+        .map_err(|_| ImportError::Parse(line, format!("invalid index `{}`", token)))?;
+    let resolved = if index < 0 { count as i64 + index } else { index - 1 };
+
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(ImportError::Parse(line, format!("index `{}` is out of range", token)));
+    }
+    Ok(resolved as usize)
+}
+
+fn parse_floats<const N: usize>(tokens: &[&str], line: usize) -> Result<[f32; N], ImportError> {
+    let mut values = [0.0; N];
+    for (i, value) in values.iter_mut().enumerate() {
+        let token = tokens
+            .get(i)
+            .ok_or_else(|| ImportError::Parse(line, format!("expected {} values", N)))?;
+        *value = token
+            .parse()
+            .map_err(|_| ImportError::Parse(line, format!("invalid number `{}`", token)))?;
+    }
+    Ok(values)
+}
+
+/// Read the positions, texture coordinates and faces of an OBJ file, the
+/// other statements are ignored. Polygons are split into triangle fans.
+pub fn parse_obj(source: &str) -> Result<ObjMesh, ImportError> {
+    let mut positions = Vec::new();
+    let mut file_tex_coords: Vec<[f32; 2]> = Vec::new();
+    let mut tex_coords: Vec<Option<[f32; 2]>> = Vec::new();
+    let mut triangles = Vec::new();
+
+    for (number, content) in source.lines().enumerate() {
+        let line = number + 1;
+        let tokens: Vec<&str> = content.split('#').next().unwrap_or("").split_whitespace().collect();
+
+        match tokens.first() {
+            Some(&"v") => {
+                positions.push(parse_floats::<3>(&tokens[1..], line)?);
+                tex_coords.push(None);
+            }
+            Some(&"vt") => {
+                // OBJ texture coordinates start at the bottom of the image
+                let [u, v] = parse_floats::<2>(&tokens[1..], line)?;
+                file_tex_coords.push([u, 1.0 - v]);
+            }
+            Some(&"f") => {
+                let mut face = Vec::with_capacity(tokens.len() - 1);
+                for vertex in &tokens[1..] {
+                    let mut parts = vertex.split('/');
+                    let position = resolve_index(parts.next().unwrap_or(""), positions.len(), line)?;
+
+                    if let Some(tex_coord) = parts.next().filter(|part| !part.is_empty()) {
+                        let tex_coord = resolve_index(tex_coord, file_tex_coords.len(), line)?;
+                        tex_coords[position].get_or_insert(file_tex_coords[tex_coord]);
+                    }
+                    face.push(position as u32);
+                }
+
+                if face.len() < 3 {
+                    return Err(ImportError::Parse(line, "a face needs at least 3 vertices".to_string()));
+                }
+                (1..face.len() - 1).for_each(|i| triangles.push([face[0], face[i], face[i + 1]]));
+            }
+            _ => {}
+        }
+    }
+
+    if triangles.is_empty() {
+        return Err(ImportError::Empty);
+    }
+
+    Ok(ObjMesh {
+        positions,
+        tex_coords: tex_coords.into_iter().map(|tex_coord| tex_coord.unwrap_or([0.0, 0.0])).collect(),
+        triangles,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQUARE: &str = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n";
+
+    #[test]
+    fn negative_indices_count_from_the_last_position() {
+        let mesh = parse_obj(&format!("{}f -4 -3 -2\n", SQUARE)).unwrap();
+        assert_eq!(mesh.triangles, vec![[0, 1, 2]]);
+    }
+
+    #[test]
+    fn out_of_range_indices_are_rejected() {
+        for face in ["f 1 2 5", "f 0 1 2", "f 1 2 -5"] {
+            let error = parse_obj(&format!("{}{}\n", SQUARE, face)).unwrap_err();
+            assert!(matches!(error, ImportError::Parse(5, _)), "{}: {:?}", face, error);
+        }
+    }
+
+    #[test]
+    fn normal_indices_are_ignored() {
+        let source = format!("{}vt 0 0\nvt 1 0\nvt 1 1\nvn 0 0 1\nf 1//1 2//1 3//1\nf 1/1/1 2/2/1 3/3/1\n", SQUARE);
+        let mesh = parse_obj(&source).unwrap();
+
+        assert_eq!(mesh.triangles, vec![[0, 1, 2], [0, 1, 2]]);
+        // Taken from the second face with the v coordinate flipped, the first one has none
+        assert_eq!(&mesh.tex_coords[..3], &[[0.0, 1.0], [1.0, 1.0], [1.0, 0.0]]);
+    }
+
+    #[test]
+    fn polygons_are_split_into_fans() {
+        let mesh = parse_obj(&format!("{}v 0.5 1.5 0\nf 1 2 3 5 4\n", SQUARE)).unwrap();
+        assert_eq!(mesh.triangles, vec![[0, 1, 2], [0, 2, 4], [0, 4, 3]]);
+    }
+
+    #[test]
+    fn faces_need_three_vertices() {
+        let error = parse_obj(&format!("{}f 1 2\n", SQUARE)).unwrap_err();
+        assert!(matches!(error, ImportError::Parse(5, _)), "{:?}", error);
+        assert!(matches!(parse_obj(SQUARE), Err(ImportError::Empty)));
+    }
+}
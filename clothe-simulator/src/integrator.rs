@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+
+use crate::node::Node;
+
+// Mass spring model integrated with the forces of the springs, or XPBD
+// where the springs are solved as distance constraints
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Solver {
+    #[default]
+    MassSpring,
+    Xpbd,
+}
+
+// Time integration scheme of the mass spring solver, selected by the entry
+// points dispatched, see `entry_points`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Integrator {
+    #[default]
+    SymplecticEuler,
+    Verlet,
+    Rk4,
+}
+
+impl Integrator {
+    /// Entry points of `compute.wgsl` dispatched in order for one substep.
+    pub fn entry_points(self) -> &'static [&'static str] {
+        match self {
+            Integrator::SymplecticEuler => &["symplectic_euler"],
+            Integrator::Verlet => &["verlet"],
+            Integrator::Rk4 => &[
+                "rk4_k1",
+                "rk4_advance_k1",
+                "rk4_k2",
+                "rk4_advance_k2",
+                "rk4_k3",
+                "rk4_advance_k3",
+                "rk4_k4",
+                "rk4_finish",
+            ],
+        }
+    }
+}
+
+// Per vertex memory of the integrators: the state at the beginning of the
+// substep (the previous position for Verlet), the last RK4 derivative and
+// the weighted sum of the RK4 derivatives
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct IntegratorState {
+    pub position: [f32; 4],
+    pub velocity: [f32; 4],
+    pub k_position: [f32; 4],
+    pub k_velocity: [f32; 4],
+    pub sum_position: [f32; 4],
+    pub sum_velocity: [f32; 4],
+}
+
+impl IntegratorState {
+    pub fn new(vertex: &Node) -> Self {
+        Self {
+            position: vertex.position,
+            velocity: vertex.velocity,
+            k_position: [0.0; 4],
+            k_velocity: [0.0; 4],
+            sum_position: [0.0; 4],
+            sum_velocity: [0.0; 4],
+        }
+    }
+}
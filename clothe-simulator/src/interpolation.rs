@@ -0,0 +1,66 @@
+use wgpu_bootstrap::wgpu;
+
+use crate::{data_containers::InterpolationData, gpu::{ComputeStage, GpuContext}, node::Node};
+
+/// Clothe drawn between its last two substeps, by the fraction of a substep
+/// the fixed timestep has accumulated but not simulated yet. The vertices are
+/// copied before the last substep of every frame, then blended into a
+/// separate vertex buffer used for the rendering only.
+pub struct RenderInterpolation {
+    data: InterpolationData,
+    data_buffer: wgpu::Buffer,
+    previous_buffer: wgpu::Buffer,
+    pub render_buffer: wgpu::Buffer,
+    snapshot: ComputeStage,
+    blend: ComputeStage,
+}
+
+impl RenderInterpolation {
+    pub fn new(context: &impl GpuContext, vertex_buffer: &wgpu::Buffer, vertices: &[Node]) -> Self {
+        let source = include_str!("shaders/interpolation.wgsl");
+        let data = InterpolationData {
+            alpha: 1.0,
+            nb_vertices: vertices.len() as u32,
+        };
+        let data_buffer = context.create_buffer(&[data], wgpu::BufferUsages::UNIFORM);
+        let previous_buffer = context.create_buffer(vertices, wgpu::BufferUsages::STORAGE);
+        let render_buffer = context.create_buffer(vertices,
+            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX);
+
+        let snapshot = ComputeStage::new(context, "Interpolation", source, "snapshot",
+            [&[(0, vertex_buffer), (1, &previous_buffer)], &[(0, &data_buffer)]], data.nb_vertices);
+        let blend = ComputeStage::new(context, "Interpolation", source, "blend",
+            [&[(0, vertex_buffer), (1, &previous_buffer), (2, &render_buffer)], &[(0, &data_buffer)]],
+            data.nb_vertices);
+
+        Self {
+            data,
+            data_buffer,
+            previous_buffer,
+            render_buffer,
+            snapshot,
+            blend,
+        }
+    }
+
+    /// Keeps the current vertices as the state the next frame blends from.
+    pub fn snapshot<'a>(&'a self, compute_pass: &mut wgpu::ComputePass<'a>) {
+        self.snapshot.dispatch(compute_pass);
+    }
+
+    pub fn set_alpha(&mut self, context: &impl GpuContext, alpha: f32) {
+        self.data.alpha = alpha;
+        context.update_buffer(&self.data_buffer, &[self.data]);
+    }
+
+    pub fn blend<'a>(&'a self, compute_pass: &mut wgpu::ComputePass<'a>) {
+        self.blend.dispatch(compute_pass);
+    }
+
+    /// Starts again from the given vertices, without any motion to blend.
+    pub fn reset(&mut self, context: &impl GpuContext, vertices: &[Node]) {
+        context.update_buffer(&self.previous_buffer, vertices);
+        context.update_buffer(&self.render_buffer, vertices);
+        self.set_alpha(context, 1.0);
+    }
+}
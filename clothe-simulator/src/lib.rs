@@ -2,3 +2,26 @@ pub mod clothe;
 pub mod node;
 pub mod spring;
 pub mod data_containers;
+pub mod scene;
+pub mod cli;
+pub mod cpu_solver;
+pub mod camera;
+pub mod offscreen;
+pub mod readback;
+pub mod export;
+pub mod geometry;
+pub mod colliders;
+pub(crate) mod vector;
+pub mod gpu;
+pub mod integrator;
+pub mod self_collision;
+pub mod tear;
+pub mod wind;
+pub mod import;
+pub mod mesh;
+pub mod xpbd;
+pub mod grab;
+pub mod controls;
+pub mod overlay;
+pub mod animation;
+pub mod interpolation;
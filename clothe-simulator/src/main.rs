@@ -1,90 +1,162 @@
+use std::{cell::Cell, error::Error, io, path::PathBuf};
+
+use bytemuck::Zeroable;
 use wgpu_bootstrap::{
     application::Application,
     camera::Camera,
     cgmath,
-    computation::Computation,
     context::Context,
-    default::{Particle, Vertex},
+    default::Vertex,
     frame::Frame,
     geometry::icosphere,
-    texture::create_texture_bind_group,
     wgpu,
     window::Window,
+    winit::event::{ElementState, MouseButton, WindowEvent},
 };
 
 use clothe_simulator::{
-    clothe::Clothe, 
+    animation::{SphereAnimator, SphereMotion},
+    camera::{CameraUniform, OrbitCamera, Ray},
+    cli::Args,
+    clothe::Clothe,
+    controls::{Advance, FixedTimestep, PlaybackControls},
     node::Node, 
-    data_containers:: {Sphere, ClotheData, ComputeData}
-};
-
-// Sphere parameters
-const SPHERE: Sphere = Sphere {
-    x: 0.0,
-    y: 0.0,
-    z: 0.0,
-    radius: 1.05,
-    friction_factor: 0.1,
+    data_containers::{ClotheData, Sphere},
+    export::{self, MeshExporter},
+    geometry::{box_mesh, capsule_mesh, merge_meshes, plane_mesh, MeshInstance},
+    grab::VertexGrab,
+    gpu::{create_buffer_bind_group, create_compute_pipeline, create_storage_buffer, get_workers,
+        storage_layout_entry, uniform_layout_entry, ComputeStage, GpuContext, PipelineSequence},
+    integrator::{IntegratorState, Solver},
+    interpolation::RenderInterpolation,
+    mesh::TriangleBuffers,
+    offscreen::{HeadlessContext, OffscreenTarget},
+    overlay::Overlay,
+    readback::read_buffer,
+    scene::Scene,
+    self_collision::SelfCollision,
+    tear::TriangleMask,
+    wind::Wind,
+    xpbd::{self, XpbdState},
 };
 
 // Parameters
-const WORKER_SIZE: u32 = 255;
 const SPHERE_ORDER: u32 = 3;
-const SPRING_CONSTANT: f32 = 1200000.0;
-const GRAVITY: f32 = -9.81;
-const MASS: f32 = 1.0;
-const CLOTH_SIZE: f32 = 2.5;
-const NUMBER_SQUARES: u32 = 100;
-const DAMPING_FACTOR: f32 = 0.8;
-const CLOTHE_CENTER: &[f32; 3] = &[0.0, 2.0, 0.0]; // [x, y, z]
-const ITERATIONS: u32 = 150;
-
-// Function to calcul the number of workers to launch
-fn get_workers(nb: u32) -> u32 {
-    if nb % WORKER_SIZE == 0 {
-        nb / WORKER_SIZE
-    } else {
-        (nb / WORKER_SIZE) + 1
+// Rendered spheres are slightly smaller than the colliders so the clothe stays visible
+const SPHERE_RENDER_SCALE: f32 = 0.95;
+const CAPSULE_SEGMENTS: u32 = 24;
+const CLEAR_COLOR: wgpu::Color = wgpu::Color {
+    r: 0.1,
+    g: 0.2,
+    b: 0.3,
+    a: 1.0,
+};
+
+// Mesh already in world coordinates, drawn with the sphere pipeline
+struct StaticMesh {
+    texture_bind_group: wgpu::BindGroup,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    instance_buffer: wgpu::Buffer,
+    nb_indices: u32,
+}
+
+impl StaticMesh {
+    fn new(context: &impl GpuContext, texture_bind_group: wgpu::BindGroup, vertices: &[Vertex], indices: &[u32])
+        -> Self {
+        let instance = MeshInstance {
+            position: [0.0, 0.0, 0.0],
+            scale: 1.0,
+        };
+
+        Self {
+            texture_bind_group,
+            vertex_buffer: context.create_buffer(vertices, wgpu::BufferUsages::VERTEX),
+            index_buffer: context.create_buffer(indices, wgpu::BufferUsages::INDEX),
+            instance_buffer: context.create_buffer(&[instance], wgpu::BufferUsages::VERTEX),
+            nb_indices: indices.len() as u32,
+        }
     }
+
+    fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_bind_group(0, &self.texture_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..self.nb_indices, 0, 0..1);
+    }
+}
+
+// Offscreen rendering state, frames are written as numbered PNG files
+struct Headless {
+    target: OffscreenTarget,
+    output: PathBuf,
+    frames: u32,
+    delta_time: f32,
+    turntable: bool,
+    frame: Cell<u32>,
 }
 
 struct MyApp {
+    camera: Camera,
+    orbit_camera: OrbitCamera,
+    camera_buffer: wgpu::Buffer,
+    headless: Option<Headless>,
+    exporter: Option<MeshExporter>,
+    triangles: Vec<[u32; 3]>,
     sphere_bind_group: wgpu::BindGroup,
     clothe_diffuse_bind_group: wgpu::BindGroup,
     camera_bind_group: wgpu::BindGroup,
     sphere_pipeline: wgpu::RenderPipeline,
     sphere_buffer: wgpu::Buffer,
     sphere_index_buffer: wgpu::Buffer,
-    particle_buffer: wgpu::Buffer,
+    sphere_instance_buffer: wgpu::Buffer,
+    compute_sphere_buffer: wgpu::Buffer,
+    animator: SphereAnimator,
+    sphere_motion: Option<SphereMotion>,
+    nb_spheres: u32,
+    ground: Option<StaticMesh>,
+    collider_meshes: Option<StaticMesh>,
     pipeline: wgpu::RenderPipeline,
-    compute_pipeline: wgpu::ComputePipeline,
+    compute_pipelines: PipelineSequence,
     distance_pipeline: wgpu::ComputePipeline,
-    normal_pipeline: wgpu::ComputePipeline,
+    normal_stage: ComputeStage,
+    self_collision: Option<SelfCollision>,
+    triangle_mask: Option<TriangleMask>,
+    wind: Option<Wind>,
+    grab: VertexGrab,
+    controls: PlaybackControls,
+    timestep: FixedTimestep,
+    interpolation: Option<RenderInterpolation>,
+    overlay: Option<Overlay>,
+    time: f32,
     vertex_buffer: wgpu::Buffer,
+    spring_buffer: wgpu::Buffer,
+    solver_state_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
     compute_vertex_bind_group: wgpu::BindGroup,
     compute_data_buffer: wgpu::Buffer,
+    compute_clothe_data_buffer: wgpu::Buffer,
     compute_data_bind_group: wgpu::BindGroup,
     compute_distance_bind_group: wgpu::BindGroup,
     distance_vertex_bind_group: wgpu::BindGroup,
-    normal_vertex_bind_group: wgpu::BindGroup,
-    normal_bind_group: wgpu::BindGroup,
-    indices: Vec<u16>,
+    // Initial state of the clothe, uploaded again on reset
+    clothe: Clothe,
     clothe_data: ClotheData,
+    scene: Scene,
+    // Where the parameter panel saves the scene
+    scene_path: PathBuf,
 }
 
 impl MyApp {
-    fn new(context: &Context) -> Self {
-        // Add texture for the sphere and the clothe
-        let sphere_texture = context.create_srgb_texture("golf-ball.jpg", 
+    fn new(context: &impl GpuContext, scene: Scene, scene_path: PathBuf, clothe: Clothe, headless: Option<Headless>,
+        exporter: Option<MeshExporter>) -> Self {
+        // Create bind groups for the textures of the sphere and the clothe
+        let sphere_bind_group = context.create_texture_bind_group("golf-ball.jpg", 
             include_bytes!("textures/golf-ball.jpg"));
-        let clothe_texture = context.create_srgb_texture("clothe-texture.jpg", 
+        let clothe_diffuse_bind_group = context.create_texture_bind_group("clothe-texture.jpg", 
             include_bytes!("textures/clothe-texture.jpg"));
 
-        // Create bind groups for texture
-        let sphere_bind_group = create_texture_bind_group(context, &sphere_texture);
-        let clothe_diffuse_bind_group = create_texture_bind_group(context, &clothe_texture);
-
         // Configure the camera parameters
         let camera = Camera {
             eye: (3.0, 1.0, 0.0).into(),
@@ -95,23 +167,24 @@ impl MyApp {
             znear: 0.1,
             zfar: 100.0,
         };
-        let (_camera_buffer, camera_bind_group) = camera.create_camera_bind_group(context);
+        // Filled by `update_camera` before the first frame
+        let camera_buffer = context.create_buffer(&[CameraUniform::zeroed()], wgpu::BufferUsages::UNIFORM);
+        let camera_bind_group = create_buffer_bind_group(context, "Camera Bind Group",
+            context.camera_bind_group_layout(), &[(0, &camera_buffer)]);
+        let orbit_camera = OrbitCamera::new(camera.eye, camera.target);
 
         /**********************************************************************************
          *                                Sphere Render
          **********************************************************************************/
-         let particle = Particle {
-            position: [SPHERE.x, SPHERE.y, SPHERE.z],
-            velocity: [0.0, 0.0, 0.0],
-        };
+         let spheres = scene.spheres();
         let (vertices, indices) = icosphere(SPHERE_ORDER);
         let sphere_pipeline = context.create_render_pipeline(
             "Sphere Render Pipeline",
             include_str!("shaders/sphere_shader.wgsl"),
-            &[Vertex::desc(), Particle::desc()],
+            &[Vertex::desc(), MeshInstance::desc()],
             &[
-                &context.texture_bind_group_layout,
-                &context.camera_bind_group_layout,
+                context.texture_bind_group_layout(),
+                context.camera_bind_group_layout(),
             ],
             wgpu::PrimitiveTopology::TriangleList,
         );
@@ -120,19 +193,42 @@ impl MyApp {
         let sphere_buffer = context.create_buffer(vertices.as_slice(), wgpu::BufferUsages::VERTEX);
         let sphere_index_buffer =
             context.create_buffer(indices.as_slice(), wgpu::BufferUsages::INDEX);
-        let particle_buffer = context.create_buffer(&[particle], wgpu::BufferUsages::VERTEX);
+        let sphere_instance_buffer = context.create_buffer(&sphere_instances(&spheres), wgpu::BufferUsages::VERTEX);
+
+        /**********************************************************************************
+         *                           Boxes and Capsules Render
+         **********************************************************************************/
+        let boxes = scene.boxes();
+        let capsules = scene.capsules();
+        let collider_meshes = (!boxes.is_empty() || !capsules.is_empty()).then(|| {
+            let meshes = boxes
+                .iter()
+                .map(box_mesh)
+                .chain(capsules.iter().map(|capsule| capsule_mesh(capsule, CAPSULE_SEGMENTS)))
+                .collect();
+            let (vertices, indices) = merge_meshes(meshes);
+
+            let texture_bind_group = context.create_texture_bind_group("golf-ball.jpg",
+                include_bytes!("textures/golf-ball.jpg"));
+            StaticMesh::new(context, texture_bind_group, &vertices, &indices)
+        });
+
+        /**********************************************************************************
+         *                                Ground Render
+         **********************************************************************************/
+        let plane = scene.plane();
+        let ground = scene.plane.as_ref().map(|plane_config| {
+            let texture_bind_group = context.create_texture_bind_group("ground.png",
+                include_bytes!("textures/ground.png"));
+            let (vertices, indices) = plane_mesh(&plane, plane_config.size);
+
+            StaticMesh::new(context, texture_bind_group, &vertices, &indices)
+        });
         
         /**********************************************************************************
          *                               Clothe Render
          **********************************************************************************/
-        let clothe = Clothe::new(CLOTH_SIZE, NUMBER_SQUARES, CLOTHE_CENTER);
-        let clothe_data: ClotheData = ClotheData {
-            center_x: clothe.center_x,
-            center_y: clothe.center_y,
-            center_z: clothe.center_z,
-            nb_vertices: clothe.nb_vertices,
-            mass: MASS,
-        };
+        let clothe_data = scene.clothe_data(&clothe);
 
         // Create pipeline to render the clothe
         let pipeline = context.create_render_pipeline(
@@ -140,8 +236,8 @@ impl MyApp {
             include_str!("shaders/shader.wgsl"),
             &[Node::desc()],
             &[
-                &context.texture_bind_group_layout,
-                &context.camera_bind_group_layout,
+                context.texture_bind_group_layout(),
+                context.camera_bind_group_layout(),
             ],
             wgpu::PrimitiveTopology::TriangleList,
         );
@@ -149,43 +245,120 @@ impl MyApp {
         // Create buffer that contains the vertices and springs
         let vertex_buffer = context.create_buffer(
             &clothe.vertices,
-            wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE,
+            wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
         );
         let spring_buffer = context.create_buffer(
             &clothe.springs,
             wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE,
         );
-        let index_buffer = context.create_buffer(&clothe.indices, wgpu::BufferUsages::INDEX);
+        let spring_offset_buffer = context.create_buffer(&clothe.spring_offsets, wgpu::BufferUsages::STORAGE);
+        let mass_buffer = context.create_buffer(&clothe.masses, wgpu::BufferUsages::STORAGE);
+        // The torn triangles are masked on the GPU
+        let index_buffer = context.create_buffer(
+            &clothe.indices,
+            wgpu::BufferUsages::INDEX | wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        );
 
         /**********************************************************************************
          *                               Compute Shaders
          **********************************************************************************/
-        let compute_data = ComputeData {
-            spring_contant: SPRING_CONSTANT,
-            damping_factor: DAMPING_FACTOR,
-            gravity: GRAVITY,
-            delta_time: 0.0,
-        };
+        let compute_data = scene.compute_data(0.0);
 
         // Create the pipelines
-        let compute_pipeline = context.create_compute_pipeline("Compute Pipeline", 
-            include_str!("shaders/compute.wgsl"));
-        let distance_pipeline = context.create_compute_pipeline("Distance Pipeline",
-            include_str!("shaders/distance_shader.wgsl"));
-        let normal_pipeline = context.create_compute_pipeline("Normal Pipeline",
-            include_str!("shaders/normal_shader.wgsl"));
+        // Every entry point of the solver shares the same bind groups
+        let compute_vertex_layout = context.device().create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Compute Vertex Layout"),
+            entries: &[
+                storage_layout_entry(0, false),
+                storage_layout_entry(1, false), // The XPBD solver keeps its multipliers in the springs
+                storage_layout_entry(2, false),
+                storage_layout_entry(3, true),
+                storage_layout_entry(4, true),
+            ],
+        });
+        let compute_data_layout = context.device().create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Compute Data Layout"),
+            entries: &[
+                storage_layout_entry(0, true),
+                uniform_layout_entry(1),
+                uniform_layout_entry(2),
+                uniform_layout_entry(3),
+                storage_layout_entry(4, true),
+                storage_layout_entry(5, true),
+            ],
+        });
+        let compute_pipelines = match scene.simulation.solver {
+            Solver::MassSpring => PipelineSequence::new(
+                context,
+                "Compute Pipeline",
+                concat!(
+                    include_str!("shaders/colliders.wgsl"),
+                    include_str!("shaders/contacts.wgsl"),
+                    include_str!("shaders/compute.wgsl"),
+                ),
+                scene.simulation.integrator.entry_points(),
+                &[&compute_vertex_layout, &compute_data_layout],
+            ),
+            Solver::Xpbd => PipelineSequence::new(
+                context,
+                "XPBD Pipeline",
+                concat!(
+                    include_str!("shaders/colliders.wgsl"),
+                    include_str!("shaders/contacts.wgsl"),
+                    include_str!("shaders/xpbd.wgsl"),
+                ),
+                &xpbd::entry_points(scene.simulation.constraint_iterations),
+                &[&compute_vertex_layout, &compute_data_layout],
+            ),
+        };
+        let distance_pipeline = create_compute_pipeline(context, "Distance Pipeline",
+            include_str!("shaders/distance_shader.wgsl"), "main");
+
+        let self_collision = scene.self_collision_data(&clothe).map(|data| {
+            SelfCollision::new(context, &vertex_buffer, &spring_buffer, &spring_offset_buffer, data)
+        });
+        let triangle_mask = (scene.material.tear_strain > 0.0).then(|| {
+            TriangleMask::new(context, &spring_buffer, &spring_offset_buffer, &index_buffer, &clothe.indices)
+        });
 
         // Create the buffers
-        let compute_sphere_buffer = context.create_buffer(&[SPHERE], wgpu::BufferUsages::UNIFORM);
+        let compute_sphere_buffer = create_storage_buffer(context, &spheres, wgpu::BufferUsages::empty());
+        let compute_box_buffer = create_storage_buffer(context, &boxes, wgpu::BufferUsages::empty());
+        let compute_capsule_buffer = create_storage_buffer(context, &capsules, wgpu::BufferUsages::empty());
         let compute_data_buffer = context.create_buffer(&[compute_data], 
             wgpu::BufferUsages::UNIFORM);
         let compute_clothe_data_buffer = context.create_buffer(&[clothe_data], 
             wgpu::BufferUsages::UNIFORM);
+        let compute_plane_buffer = context.create_buffer(&[plane], wgpu::BufferUsages::UNIFORM);
+
+        // Per vertex memory of the solver
+        let solver_state_buffer = context.create_buffer(&solver_states(scene.simulation.solver, &clothe.vertices),
+            wgpu::BufferUsages::STORAGE);
+
+        let triangles = export::unique_triangles(&clothe.indices);
+        let triangle_buffers = TriangleBuffers::new(context, &triangles, clothe.nb_vertices);
+        let wind = scene.wind_data(triangles.len() as u32).map(|data| {
+            Wind::new(context, &vertex_buffer, &compute_data_buffer, &compute_clothe_data_buffer,
+                &triangle_buffers, &mass_buffer, data)
+        });
+        let normal_stage = ComputeStage::new(context, "Normal", include_str!("shaders/normal_shader.wgsl"), "main",
+            [
+                &[(0, &vertex_buffer), (1, &triangle_buffers.triangles), (2, &triangle_buffers.offsets),
+                    (3, &triangle_buffers.adjacent), (4, &spring_buffer), (5, &spring_offset_buffer)],
+                &[(0, &compute_clothe_data_buffer)],
+            ],
+            clothe.nb_vertices);
+        let grab = VertexGrab::new(context, &vertex_buffer, &compute_data_buffer);
+        let interpolation = scene.simulation.interpolate
+            .then(|| RenderInterpolation::new(context, &vertex_buffer, &clothe.vertices));
+        let sphere_motion = SphereAnimator::is_animated(&scene.spheres).then(|| {
+            SphereMotion::new(context, &compute_sphere_buffer, &compute_data_buffer, spheres.len() as u32)
+        });
 
         // Create the bind groups
         let compute_vertex_bind_group = context.create_bind_group(
             "Compute Bind Group",
-            &compute_pipeline.get_bind_group_layout(0),
+            &compute_vertex_layout,
             &[
                 wgpu::BindGroupEntry {
                     binding: 0,
@@ -195,6 +368,18 @@ impl MyApp {
                     binding: 1,
                     resource: spring_buffer.as_entire_binding(),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: solver_state_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: spring_offset_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: mass_buffer.as_entire_binding(),
+                },
             ],
         );
 
@@ -210,20 +395,9 @@ impl MyApp {
                     binding: 1,
                     resource: spring_buffer.as_entire_binding(),
                 },
-            ],
-        );
-
-        let normal_vertex_bind_group = context.create_bind_group(
-            "Normal Bind Group",
-            &normal_pipeline.get_bind_group_layout(0),
-            &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: vertex_buffer.as_entire_binding(),
-                },
                 wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: spring_buffer.as_entire_binding(),
+                    binding: 2,
+                    resource: spring_offset_buffer.as_entire_binding(),
                 },
             ],
         );
@@ -231,7 +405,7 @@ impl MyApp {
         
         let compute_data_bind_group = context.create_bind_group(
             "Compute Data",
-            &compute_pipeline.get_bind_group_layout(1),
+            &compute_data_layout,
             &[
                 wgpu::BindGroupEntry {
                     binding: 0,
@@ -245,6 +419,18 @@ impl MyApp {
                     binding: 2,
                     resource: compute_clothe_data_buffer.as_entire_binding(),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: compute_plane_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: compute_box_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: compute_capsule_buffer.as_entire_binding(),
+                },
             ],
         );
 
@@ -259,18 +445,15 @@ impl MyApp {
             ],
         );
 
-        let normal_bind_group = context.create_bind_group(
-            "Normal Data",
-            &normal_pipeline.get_bind_group_layout(1),
-            &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: compute_clothe_data_buffer.as_entire_binding(),
-                },
-            ],
-        );
-
-        Self {
+        let app = Self {
+            // Only in the window, added once the app is created
+            overlay: None,
+            camera,
+            orbit_camera,
+            camera_buffer,
+            headless,
+            exporter,
+            triangles,
             sphere_bind_group,
             clothe_diffuse_bind_group,
             camera_bind_group,
@@ -278,76 +461,318 @@ impl MyApp {
             sphere_pipeline,
             sphere_buffer,
             sphere_index_buffer,
-            particle_buffer,
-            compute_pipeline,
+            sphere_instance_buffer,
+            compute_sphere_buffer,
+            animator: SphereAnimator::new(&scene.spheres),
+            sphere_motion,
+            nb_spheres: spheres.len() as u32,
+            ground,
+            collider_meshes,
+            compute_pipelines,
             distance_pipeline,
-            normal_pipeline,
+            normal_stage,
+            self_collision,
+            triangle_mask,
+            wind,
+            grab,
+            controls: PlaybackControls::default(),
+            timestep: FixedTimestep::new(scene.time_step()),
+            interpolation,
+            time: 0.0,
             vertex_buffer,
+            spring_buffer,
+            solver_state_buffer,
             index_buffer,
             compute_vertex_bind_group,
             compute_data_bind_group,
             compute_data_buffer,
+            compute_clothe_data_buffer,
             compute_distance_bind_group,
             distance_vertex_bind_group,
-            normal_vertex_bind_group,
-            normal_bind_group,
-            indices: clothe.indices.clone(),
+            clothe,
             clothe_data,
+            scene,
+            scene_path,
+        };
+
+        app.update_camera(context);
+
+        app
+    }
+
+    fn update_camera(&self, context: &impl GpuContext) {
+        let mut orbit_camera = self.orbit_camera.clone();
+
+        // Turn around the vertical axis once over the whole sequence
+        if let Some(headless) = self.headless.as_ref().filter(|h| h.turntable) {
+            let turn = headless.frame.get() as f32 / headless.frames as f32;
+            orbit_camera.yaw += 2.0 * std::f32::consts::PI * turn;
         }
+
+        let camera_uniform = CameraUniform::new(orbit_camera.eye(), orbit_camera.target, self.camera.up,
+            self.camera.aspect, self.camera.fovy, self.camera.znear, self.camera.zfar);
+        context.update_buffer(&self.camera_buffer, &[camera_uniform]);
     }
-}
 
-impl Application for MyApp {
-    fn render(&self, context: &Context) -> Result<(), wgpu::SurfaceError> {
-        let mut frame = Frame::new(context)?;
+    // Back to the initial state of the clothe and of the solver
+    fn reset(&mut self, context: &impl GpuContext) {
+        context.update_buffer(&self.vertex_buffer, &self.clothe.vertices);
+        context.update_buffer(&self.spring_buffer, &self.clothe.springs);
+        context.update_buffer(&self.solver_state_buffer,
+            &solver_states(self.scene.simulation.solver, &self.clothe.vertices));
+        context.update_buffer(&self.index_buffer, &self.clothe.indices);
+        self.triangles = export::unique_triangles(&self.clothe.indices);
+        self.grab.release(context);
+        if let Some(interpolation) = &mut self.interpolation {
+            interpolation.reset(context, &self.clothe.vertices);
+        }
 
-        {
-            let mut render_pass = frame.begin_render_pass(wgpu::Color {
-                r: 0.1,
-                g: 0.2,
-                b: 0.3,
-                a: 1.0,
+        self.time = 0.0;
+        self.timestep.reset();
+        if let Some(wind) = &mut self.wind {
+            wind.set_time(context, self.time);
+        }
+        self.animator.reset();
+        self.update_spheres(context, 0.0);
+    }
+
+    // Spheres at the current time, moving for `delta_time`. The rendered ones
+    // are drawn where the substeps leave them.
+    fn update_spheres(&mut self, context: &impl GpuContext, delta_time: f32) {
+        let mut spheres = self.animator.spheres(&self.scene.spheres, self.time, delta_time);
+        context.update_buffer(&self.compute_sphere_buffer, &spheres);
+
+        spheres.iter_mut().for_each(|sphere| sphere.advance(delta_time));
+        context.update_buffer(&self.sphere_instance_buffer, &sphere_instances(&spheres));
+    }
+
+    // Parameter panel of the overlay, the changed values are written to the
+    // uniform buffers right away
+    fn parameter_panel(&mut self, ui_context: &egui::Context, context: &impl GpuContext) {
+        let mut clothe_changed = false;
+        let mut spheres_changed = false;
+        let mut reset = false;
+        let mut save = false;
+
+        egui::Window::new("Parameters").show(ui_context, |ui| {
+            let simulation = &mut self.scene.simulation;
+            ui.collapsing("Simulation", |ui| {
+                match simulation.solver {
+                    Solver::MassSpring => ui.add(egui::Slider::new(&mut simulation.spring_constant, 1.0..=1.0e7)
+                        .logarithmic(true).text("Spring constant")),
+                    Solver::Xpbd => ui.add(egui::Slider::new(&mut simulation.compliance, 0.0..=1.0e-3)
+                        .logarithmic(true).text("Compliance")),
+                };
+                ui.add(egui::Slider::new(&mut simulation.damping_factor, 0.0..=10.0).text("Damping factor"));
+                ui.add(egui::Slider::new(&mut simulation.gravity, -20.0..=0.0).text("Gravity"));
+                match &mut simulation.time_step {
+                    Some(time_step) => ui.add(egui::Slider::new(time_step, 1.0e-5..=1.0e-2)
+                        .logarithmic(true).text("Time step")),
+                    None => ui.add(egui::Slider::new(&mut simulation.iterations, 1..=500)
+                        .text("Substeps per 1/60 s")),
+                };
             });
 
-            // Clothe render pipeline
-            render_pass.set_pipeline(&self.pipeline);
-            render_pass.set_bind_group(0, &self.clothe_diffuse_bind_group, &[]);
-            render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-            render_pass.draw_indexed(0..(self.indices.len() as u32), 0, 0..1);
+            // Tearing can't be enabled once started, the torn triangles wouldn't be hidden
+            if self.triangle_mask.is_some() {
+                ui.collapsing("Material", |ui| {
+                    clothe_changed |= ui.add(egui::Slider::new(&mut self.scene.material.tear_strain, 0.01..=1.0)
+                        .text("Tear strain")).changed();
+                });
+            }
+
+            for (index, sphere) in self.scene.spheres.iter_mut().enumerate() {
+                ui.collapsing(format!("Sphere {}", index + 1), |ui| {
+                    for (value, axis) in sphere.center.iter_mut().zip(["x", "y", "z"]) {
+                        spheres_changed |= ui.add(egui::Slider::new(value, -5.0..=5.0).text(axis)).changed();
+                    }
+                    spheres_changed |= ui.add(egui::Slider::new(&mut sphere.radius, 0.05..=3.0).text("Radius"))
+                        .changed();
+                    spheres_changed |= ui.add(egui::Slider::new(&mut sphere.friction_factor, 0.0..=1.0)
+                        .text("Friction")).changed();
+                });
+            }
+
+            ui.horizontal(|ui| {
+                reset = ui.button("Reset").clicked();
+                save = ui.button("Save").on_hover_text(self.scene_path.display().to_string()).clicked();
+            });
+        });
+
+        if clothe_changed {
+            self.clothe_data.tear_strain = self.scene.material.tear_strain;
+            context.update_buffer(&self.compute_clothe_data_buffer, &[self.clothe_data]);
+        }
+        if spheres_changed {
+            self.update_spheres(context, 0.0);
+        }
+        if reset {
+            self.reset(context);
+        }
+        if save {
+            match self.scene.save(&self.scene_path) {
+                Ok(()) => println!("Scene saved to `{}`", self.scene_path.display()),
+                Err(error) => eprintln!("error: {}", error),
+            }
+        }
+    }
+
+    // Ray from the eye through the mouse cursor
+    fn cursor_ray(&self, context: &Context) -> Option<Ray> {
+        self.orbit_camera.cursor().map(|cursor| {
+            self.orbit_camera.ray(cursor, context.config.width, context.config.height, self.camera.fovy)
+        })
+    }
+
+    fn export_mesh(&mut self, context: &impl GpuContext) {
+        let Some(exporter) = self.exporter.as_mut() else {
+            return;
+        };
+
+        if !exporter.next_frame() {
+            return;
+        }
+
+        let vertices: Vec<Node> = read_buffer(context.device(), context.queue(), &self.vertex_buffer,
+            self.clothe_data.nb_vertices as usize);
+
+        // Torn triangles are not exported
+        if self.triangle_mask.is_some() {
+            let indices: Vec<u32> = read_buffer(context.device(), context.queue(), &self.index_buffer,
+                self.clothe.indices.len());
+            self.triangles = export::unique_triangles(&indices);
+        }
+
+        if let Err(error) = exporter.export(&vertices, &self.triangles) {
+            eprintln!("error: cannot export the clothe mesh: {}", error);
+            self.exporter = None;
+        }
+    }
 
-            // Sphere render pipeline
-            render_pass.set_pipeline(&self.sphere_pipeline);
+    fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        // Clothe render pipeline
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.clothe_diffuse_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
+        let vertex_buffer = match &self.interpolation {
+            Some(interpolation) => &interpolation.render_buffer,
+            None => &self.vertex_buffer,
+        };
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..(self.clothe.indices.len() as u32), 0, 0..1);
+
+        // Sphere render pipeline
+        render_pass.set_pipeline(&self.sphere_pipeline);
+        render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
+
+        if self.nb_spheres > 0 {
             render_pass.set_bind_group(0, &self.sphere_bind_group, &[]);
-            render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
             render_pass.set_vertex_buffer(0, self.sphere_buffer.slice(..));
-            render_pass.set_vertex_buffer(1, self.particle_buffer.slice(..));
+            render_pass.set_vertex_buffer(1, self.sphere_instance_buffer.slice(..));
             render_pass.set_index_buffer(self.sphere_index_buffer.slice(..),
                 wgpu::IndexFormat::Uint16,);
-            render_pass.draw_indexed(0..(icosphere(SPHERE_ORDER).1.as_slice().len() as u32), 0, 0..1);
+            render_pass.draw_indexed(0..(icosphere(SPHERE_ORDER).1.as_slice().len() as u32), 0,
+                0..self.nb_spheres);
         }
 
-        frame.present();
+        // Ground, drawn with the sphere pipeline
+        if let Some(ground) = &self.ground {
+            ground.draw(render_pass);
+        }
 
+        // Boxes and capsules
+        if let Some(collider_meshes) = &self.collider_meshes {
+            collider_meshes.draw(render_pass);
+        }
+    }
+
+    // Renders the frame into the offscreen target and writes it as the next PNG file
+    fn render_offscreen(&self, context: &impl GpuContext) -> io::Result<()> {
+        let Some(headless) = &self.headless else {
+            return Ok(());
+        };
+
+        let mut encoder = context.device().create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Offscreen Encoder"),
+        });
+
+        {
+            let mut render_pass = headless.target.begin_render_pass(&mut encoder, CLEAR_COLOR);
+            self.draw(&mut render_pass);
+        }
+
+        headless.target.copy_to_buffer(&mut encoder);
+        context.queue().submit(std::iter::once(encoder.finish()));
+
+        let frame = headless.frame.get();
+        let path = headless.output.join(format!("frame_{:05}.png", frame));
+
+        headless.target.save_png(context.device(), &path).map_err(|error| {
+            io::Error::new(error.kind(), format!("cannot write `{}`: {}", path.display(), error))
+        })?;
+
+        headless.frame.set(frame + 1);
         Ok(())
     }
 
-    fn update(&mut self, context: &Context, delta_time: f32) {
-        let compute_data = ComputeData {
-            spring_contant: SPRING_CONSTANT,
-            damping_factor: DAMPING_FACTOR,
-            gravity: GRAVITY,
-            delta_time: delta_time / ITERATIONS as f32,
+    // Runs the substeps of a frame lasting `delta_time` seconds
+    fn simulate(&mut self, context: &impl GpuContext, delta_time: f32) {
+        // Offscreen sequences advance by a fixed frame duration. In the window
+        // the time of a slow frame is capped so a hitch isn't caught up with
+        // an ever growing number of substeps.
+        self.timestep.time_step = self.scene.time_step();
+        let advance = match &self.headless {
+            Some(headless) => Advance::Time(headless.delta_time),
+            None => match self.controls.advance(delta_time) {
+                Advance::Time(frame_time) => Advance::Time(frame_time.min(self.scene.simulation.max_frame_time)),
+                Advance::Substep => Advance::Substep,
+            },
         };
+        let substeps = match advance {
+            Advance::Time(frame_time) => self.timestep.advance(frame_time),
+            Advance::Substep => 1,
+        };
+        if substeps == 0 && self.interpolation.is_none() {
+            return;
+        }
+
+        let time_step = self.timestep.time_step;
+        let delta_time = substeps as f32 * time_step;
+        let compute_data = self.scene.compute_data(time_step);
         context.update_buffer(&self.compute_data_buffer, &[compute_data]);
 
+        if self.sphere_motion.is_some() && substeps > 0 {
+            self.update_spheres(context, delta_time);
+        }
+
+        // The wind moves its gusts forward at each substep
+        if let Some(wind) = &mut self.wind {
+            wind.set_time(context, self.time);
+        }
+        self.time += delta_time;
+
+        if let Some(interpolation) = &mut self.interpolation {
+            interpolation.set_alpha(context, self.timestep.alpha());
+        }
+
         let compute_nb: u32 = get_workers(self.clothe_data.nb_vertices);
-        let mut computation = Computation::new(context);
-    
+        let mut encoder = context.device().create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Compute Encoder"),
+        });
 
-        for _ in 0..ITERATIONS {
-            let mut compute_pass = computation.begin_compute_pass();
+        for substep in 0..substeps {
+            let mut compute_pass = begin_compute_pass(&mut encoder);
+
+            // State the rendering blends from
+            if let Some(interpolation) = self.interpolation.as_ref().filter(|_| substep + 1 == substeps) {
+                interpolation.snapshot(&mut compute_pass);
+            }
+
+            // Kinematic spheres
+            if let Some(sphere_motion) = &self.sphere_motion {
+                sphere_motion.dispatch(&mut compute_pass);
+            }
 
             // Distance pipeline
             compute_pass.set_pipeline(&self.distance_pipeline);
@@ -355,28 +780,240 @@ impl Application for MyApp {
             compute_pass.set_bind_group(1, &self.compute_distance_bind_group, &[]);
             compute_pass.dispatch_workgroups(compute_nb, 1, 1);
 
-            // Compute pipeline
-            compute_pass.set_pipeline(&self.compute_pipeline);
-            compute_pass.set_bind_group(0, &self.compute_vertex_bind_group, &[]);
-            compute_pass.set_bind_group(1, &self.compute_data_bind_group, &[]);
-            compute_pass.dispatch_workgroups(compute_nb, 1, 1);
+            // Wind pipelines
+            if let Some(wind) = &self.wind {
+                wind.dispatch(&mut compute_pass);
+            }
+
+            // Compute pipelines, one per stage of the solver
+            self.compute_pipelines.dispatch(
+                &mut compute_pass,
+                &[&self.compute_vertex_bind_group, &self.compute_data_bind_group],
+                compute_nb,
+            );
+
+            // Vertex held with the mouse
+            self.grab.dispatch(&mut compute_pass);
+
+            // Self collision pipelines
+            if let Some(self_collision) = &self.self_collision {
+                self_collision.dispatch(&mut compute_pass);
+            }
 
             // Normal pipeline
-            compute_pass.set_pipeline(&self.normal_pipeline);
-            compute_pass.set_bind_group(0, &self.normal_vertex_bind_group, &[]);
-            compute_pass.set_bind_group(1, &self.normal_bind_group, &[]);
-            compute_pass.dispatch_workgroups(compute_nb, 1, 1);
+            self.normal_stage.dispatch(&mut compute_pass);
+        }
+
+        // Hide the triangles torn during this frame
+        if let Some(triangle_mask) = self.triangle_mask.as_ref().filter(|_| substeps > 0) {
+            let mut compute_pass = begin_compute_pass(&mut encoder);
+            triangle_mask.dispatch(&mut compute_pass);
+        }
+
+        if let Some(interpolation) = &self.interpolation {
+            let mut compute_pass = begin_compute_pass(&mut encoder);
+            interpolation.blend(&mut compute_pass);
+        }
+
+        context.queue().submit(std::iter::once(encoder.finish()));
+
+        if substeps > 0 {
+            self.export_mesh(context);
+        }
+    }
+}
+
+impl Application for MyApp {
+    fn render(&self, context: &Context) -> Result<(), wgpu::SurfaceError> {
+        let mut frame = Frame::new(context)?;
+
+        {
+            let mut render_pass = frame.begin_render_pass(CLEAR_COLOR);
+            self.draw(&mut render_pass);
+            if let Some(overlay) = &self.overlay {
+                overlay.draw(&mut render_pass);
+            }
         }
 
-        computation.submit();
+        frame.present();
+
+        Ok(())
+    }
+
+    fn update(&mut self, context: &Context, delta_time: f32) {
+        self.update_camera(context);
+
+        // The grabbed vertex follows the cursor, even when only the camera moves
+        if self.grab.is_active() {
+            if let Some(ray) = self.cursor_ray(context) {
+                self.grab.drag(context, &ray);
+            }
+        }
+
+        // The panel runs even while paused
+        if let Some(mut overlay) = self.overlay.take() {
+            let ui_context = overlay.begin_frame(context);
+            self.parameter_panel(&ui_context, context);
+            overlay.end_frame(context);
+            self.overlay = Some(overlay);
+        }
+
+        if self.controls.take_reset() {
+            self.reset(context);
+        }
+
+        self.simulate(context, delta_time);
+    }
+
+    fn input(&mut self, event: &WindowEvent, context: &Context) -> bool {
+        if let WindowEvent::Resized(size) = event {
+            if size.height > 0 && self.headless.is_none() {
+                self.camera.aspect = size.width as f32 / size.height as f32;
+            }
+            return false;
+        }
+
+        // The overlay gets the events first, the scene ignores those it used
+        if let Some(overlay) = &mut self.overlay {
+            if overlay.input(event) {
+                return true;
+            }
+        }
+
+        if self.orbit_camera.input(event) || self.controls.input(event) || self.animator.input(event) {
+            return true;
+        }
+
+        // The left button grabs the vertex under the cursor
+        match event {
+            WindowEvent::MouseInput { state: ElementState::Pressed, button: MouseButton::Left, .. } => {
+                let Some(ray) = self.cursor_ray(context) else {
+                    return false;
+                };
+                let vertices: Vec<Node> = read_buffer(context.device(), context.queue(), &self.vertex_buffer,
+                    self.clothe_data.nb_vertices as usize);
+                self.grab.grab(context, &vertices, &ray)
+            }
+            WindowEvent::MouseInput { state: ElementState::Released, button: MouseButton::Left, .. } => {
+                let active = self.grab.is_active();
+                self.grab.release(context);
+                active
+            }
+            _ => false,
+        }
+    }
+}
+
+fn begin_compute_pass(encoder: &mut wgpu::CommandEncoder) -> wgpu::ComputePass<'_> {
+    encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+        label: Some("Compute Pass"),
+        timestamp_writes: None,
+    })
+}
+
+// Instances of the rendered spheres, the unit icosphere is scaled by the radius
+fn sphere_instances(spheres: &[Sphere]) -> Vec<MeshInstance> {
+    if spheres.is_empty() {
+        return vec![MeshInstance { position: [0.0; 3], scale: 0.0 }];
+    }
+
+    spheres
+        .iter()
+        .map(|sphere| MeshInstance {
+            position: [sphere.x, sphere.y, sphere.z],
+            scale: sphere.radius * SPHERE_RENDER_SCALE,
+        })
+        .collect()
+}
+
+// Per vertex memory of the solver, as bytes since its type depends on the solver
+fn solver_states(solver: Solver, vertices: &[Node]) -> Vec<u8> {
+    match solver {
+        Solver::MassSpring => {
+            let states: Vec<IntegratorState> = vertices.iter().map(IntegratorState::new).collect();
+            bytemuck::cast_slice(&states).to_vec()
+        }
+        Solver::Xpbd => {
+            let states: Vec<XpbdState> = vertices.iter().map(XpbdState::new).collect();
+            bytemuck::cast_slice(&states).to_vec()
+        }
     }
 }
 
 fn main() {
+    let args = Args::parse(std::env::args().skip(1)).unwrap_or_else(|error| {
+        eprintln!("error: {}", error);
+        eprintln!("{}", Args::USAGE);
+        std::process::exit(2);
+    });
+
+    // Fall back to the default scene when no file is given
+    let scene = match &args.scene {
+        Some(path) => Scene::load(path).unwrap_or_else(|error| {
+            eprintln!("error: {}", error);
+            std::process::exit(1);
+        }),
+        None => Scene::default(),
+    };
+    let exporter = args.export.as_ref().map(|directory| {
+        MeshExporter::new(directory, args.export_format, args.export_every).unwrap_or_else(|error| {
+            eprintln!("error: cannot create `{}`: {}", directory.display(), error);
+            std::process::exit(1);
+        })
+    });
+    // The parameter panel saves next to the scene file, never over it: the
+    // comments of the scene file would be lost
+    let scene_path = match &args.scene {
+        Some(path) => path.with_extension("saved.toml"),
+        None => PathBuf::from("scene.saved.toml"),
+    };
+
+    if args.headless {
+        if let Err(error) = run_headless(&args, scene, scene_path, exporter) {
+            eprintln!("error: {}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let window = Window::new();
 
     let context = window.get_context();
-    let my_app = MyApp::new(context);
+    // The clothe is built once the device limits are known
+    let clothe = scene.build_gpu_clothe(&context.device.limits()).unwrap_or_else(|error| {
+        eprintln!("error: {}", error);
+        std::process::exit(1);
+    });
+
+    let mut my_app = MyApp::new(context, scene, scene_path, clothe, None, exporter);
+    my_app.overlay = Some(Overlay::new(context));
 
     window.run(my_app);
 }
+
+// Renders the sequence without any window, each frame is written as a PNG file
+fn run_headless(args: &Args, scene: Scene, scene_path: PathBuf, exporter: Option<MeshExporter>)
+    -> Result<(), Box<dyn Error>> {
+    std::fs::create_dir_all(&args.output)
+        .map_err(|error| format!("cannot create `{}`: {}", args.output.display(), error))?;
+
+    let context = HeadlessContext::new(args.width, args.height, args.software)?;
+    let clothe = scene.build_gpu_clothe(&context.device.limits())?;
+    let headless = Headless {
+        target: OffscreenTarget::new(&context.device, args.width, args.height, context.format),
+        output: args.output.clone(),
+        frames: args.frames,
+        delta_time: 1.0 / args.fps,
+        turntable: args.turntable,
+        frame: Cell::new(0),
+    };
+    let mut app = MyApp::new(&context, scene, scene_path, clothe, Some(headless), exporter);
+
+    for _ in 0..args.frames {
+        app.update_camera(&context);
+        app.simulate(&context, 1.0 / args.fps);
+        app.render_offscreen(&context)?;
+    }
+
+    Ok(())
+}
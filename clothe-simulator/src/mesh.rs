@@ -0,0 +1,50 @@
+use wgpu_bootstrap::wgpu;
+
+use crate::gpu::{create_storage_buffer, GpuContext};
+
+/// Triangles around every vertex, stored as offsets in a flat list: the
+/// triangles of vertex `i` are `triangles[offsets[i]..offsets[i + 1]]`.
+pub fn vertex_triangles(triangles: &[[u32; 3]], nb_vertices: u32) -> (Vec<u32>, Vec<u32>) {
+    let mut offsets = vec![0u32; nb_vertices as usize + 1];
+    for &index in triangles.iter().flatten() {
+        offsets[index as usize + 1] += 1;
+    }
+    for i in 0..nb_vertices as usize {
+        offsets[i + 1] += offsets[i];
+    }
+
+    let mut next = offsets.clone();
+    let mut adjacent = vec![0u32; offsets[nb_vertices as usize] as usize];
+    for (triangle, vertices) in triangles.iter().enumerate() {
+        for &index in vertices {
+            adjacent[next[index as usize] as usize] = triangle as u32;
+            next[index as usize] += 1;
+        }
+    }
+
+    (offsets, adjacent)
+}
+
+/// Triangles of the clothe on the GPU with the triangles around each vertex,
+/// shared by the normal and wind passes.
+pub struct TriangleBuffers {
+    pub triangles: wgpu::Buffer,
+    pub offsets: wgpu::Buffer,
+    pub adjacent: wgpu::Buffer,
+    pub nb_triangles: u32,
+    pub nb_vertices: u32,
+}
+
+impl TriangleBuffers {
+    pub fn new(context: &impl GpuContext, triangles: &[[u32; 3]], nb_vertices: u32) -> Self {
+        let (offsets, adjacent) = vertex_triangles(triangles, nb_vertices);
+
+        Self {
+            triangles: create_storage_buffer(context, triangles, wgpu::BufferUsages::empty()),
+            offsets: create_storage_buffer(context, &offsets, wgpu::BufferUsages::empty()),
+            adjacent: create_storage_buffer(context, &adjacent, wgpu::BufferUsages::empty()),
+            nb_triangles: triangles.len() as u32,
+            nb_vertices,
+        }
+    }
+}
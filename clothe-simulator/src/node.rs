@@ -6,7 +6,8 @@ pub struct Node {
     pub position: [f32; 4],
     pub normal: [f32; 4],
     pub velocity: [f32; 4],
-    pub resultant: [f32; 4],
+    pub resultant: [f32; 3],
+    pub pinned: u32,
     pub tex_coords: [f32; 4],
 }
 
@@ -0,0 +1,202 @@
+use std::time::Instant;
+
+use wgpu_bootstrap::{
+    context::Context,
+    wgpu,
+    winit::{
+        event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent},
+        keyboard::{KeyCode, PhysicalKey},
+    },
+};
+
+use crate::offscreen::DEPTH_FORMAT;
+
+// Points scrolled by one line of the mouse wheel
+const SCROLL_LINE_POINTS: f32 = 50.0;
+
+// Keys used to edit the values typed in the overlay
+fn egui_key(key: PhysicalKey) -> Option<egui::Key> {
+    let PhysicalKey::Code(code) = key else {
+        return None;
+    };
+
+    Some(match code {
+        KeyCode::Enter | KeyCode::NumpadEnter => egui::Key::Enter,
+        KeyCode::Escape => egui::Key::Escape,
+        KeyCode::Tab => egui::Key::Tab,
+        KeyCode::Backspace => egui::Key::Backspace,
+        KeyCode::Delete => egui::Key::Delete,
+        KeyCode::ArrowLeft => egui::Key::ArrowLeft,
+        KeyCode::ArrowRight => egui::Key::ArrowRight,
+        KeyCode::ArrowUp => egui::Key::ArrowUp,
+        KeyCode::ArrowDown => egui::Key::ArrowDown,
+        KeyCode::Home => egui::Key::Home,
+        KeyCode::End => egui::Key::End,
+        KeyCode::KeyA => egui::Key::A,
+        KeyCode::KeyC => egui::Key::C,
+        KeyCode::KeyV => egui::Key::V,
+        KeyCode::KeyX => egui::Key::X,
+        _ => return None,
+    })
+}
+
+/// Immediate mode GUI drawn over the scene with egui. The window belongs to
+/// wgpu_bootstrap, so its events are translated to egui input here. A frame
+/// of the GUI is built in `update` between `begin_frame` and `end_frame`,
+/// then drawn at the end of the render pass.
+pub struct Overlay {
+    context: egui::Context,
+    renderer: egui_wgpu::Renderer,
+    input: egui::RawInput,
+    modifiers: egui::Modifiers,
+    cursor: egui::Pos2,
+    scale_factor: f32,
+    start: Instant,
+    paint_jobs: Vec<egui::ClippedPrimitive>,
+    screen: egui_wgpu::ScreenDescriptor,
+    // Textures still used by the last frame, freed after it was drawn
+    textures_to_free: Vec<egui::TextureId>,
+}
+
+impl Overlay {
+    pub fn new(context: &Context) -> Self {
+        Self {
+            context: egui::Context::default(),
+            renderer: egui_wgpu::Renderer::new(&context.device, context.config.format, Some(DEPTH_FORMAT), 1),
+            input: egui::RawInput::default(),
+            modifiers: egui::Modifiers::default(),
+            cursor: egui::Pos2::ZERO,
+            scale_factor: 1.0,
+            start: Instant::now(),
+            paint_jobs: Vec::new(),
+            screen: egui_wgpu::ScreenDescriptor {
+                size_in_pixels: [context.config.width, context.config.height],
+                pixels_per_point: 1.0,
+            },
+            textures_to_free: Vec::new(),
+        }
+    }
+
+    /// Gives a window event to the GUI, returns whether the scene should
+    /// ignore it. Cursor moves and button releases are always shared, so a
+    /// drag started in the scene ends even over the overlay.
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = egui::pos2(position.x as f32, position.y as f32) / self.scale_factor;
+                self.input.events.push(egui::Event::PointerMoved(self.cursor));
+                false
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.input.events.push(egui::Event::PointerGone);
+                false
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                let button = match button {
+                    MouseButton::Left => egui::PointerButton::Primary,
+                    MouseButton::Right => egui::PointerButton::Secondary,
+                    MouseButton::Middle => egui::PointerButton::Middle,
+                    _ => return false,
+                };
+                let pressed = *state == ElementState::Pressed;
+                self.input.events.push(egui::Event::PointerButton {
+                    pos: self.cursor,
+                    button,
+                    pressed,
+                    modifiers: self.modifiers,
+                });
+                pressed && self.context.is_pointer_over_area()
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let delta = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => egui::vec2(*x, *y) * SCROLL_LINE_POINTS,
+                    MouseScrollDelta::PixelDelta(position) => {
+                        egui::vec2(position.x as f32, position.y as f32) / self.scale_factor
+                    }
+                };
+                self.input.events.push(egui::Event::Scroll(delta));
+                self.context.is_pointer_over_area()
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                let state = modifiers.state();
+                self.modifiers = egui::Modifiers {
+                    alt: state.alt_key(),
+                    ctrl: state.control_key(),
+                    shift: state.shift_key(),
+                    mac_cmd: false,
+                    command: state.control_key() || state.super_key(),
+                };
+                false
+            }
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                self.scale_factor = *scale_factor as f32;
+                false
+            }
+            // Keys only go to the GUI while a value is being typed
+            WindowEvent::KeyboardInput { event, .. } if self.context.wants_keyboard_input() => {
+                let pressed = event.state == ElementState::Pressed;
+                if let Some(key) = egui_key(event.physical_key) {
+                    self.input.events.push(egui::Event::Key {
+                        key,
+                        physical_key: None,
+                        pressed,
+                        repeat: event.repeat,
+                        modifiers: self.modifiers,
+                    });
+                }
+                if let Some(text) = event.text.as_ref().filter(|text| !text.chars().any(char::is_control)) {
+                    if pressed && !self.modifiers.command {
+                        self.input.events.push(egui::Event::Text(text.to_string()));
+                    }
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Starts a frame of the GUI, the widgets are added to the returned context.
+    pub fn begin_frame(&mut self, context: &Context) -> egui::Context {
+        let size = egui::vec2(context.config.width as f32, context.config.height as f32) / self.scale_factor;
+
+        let mut input = std::mem::take(&mut self.input);
+        input.screen_rect = Some(egui::Rect::from_min_size(egui::Pos2::ZERO, size));
+        input.time = Some(self.start.elapsed().as_secs_f64());
+        input.modifiers = self.modifiers;
+        input.viewports.entry(egui::ViewportId::ROOT).or_default().native_pixels_per_point =
+            Some(self.scale_factor);
+
+        self.context.begin_frame(input);
+        self.context.clone()
+    }
+
+    /// Ends the frame and uploads its textures and vertices.
+    pub fn end_frame(&mut self, context: &Context) {
+        let output = self.context.end_frame();
+
+        for id in self.textures_to_free.drain(..) {
+            self.renderer.free_texture(&id);
+        }
+        for (id, image_delta) in &output.textures_delta.set {
+            self.renderer.update_texture(&context.device, &context.queue, *id, image_delta);
+        }
+        self.textures_to_free = output.textures_delta.free;
+
+        self.paint_jobs = self.context.tessellate(output.shapes, output.pixels_per_point);
+        self.screen = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [context.config.width, context.config.height],
+            pixels_per_point: output.pixels_per_point,
+        };
+
+        let mut encoder = context.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Overlay Encoder"),
+        });
+        let commands = self.renderer.update_buffers(&context.device, &context.queue, &mut encoder,
+            &self.paint_jobs, &self.screen);
+        context.queue.submit(commands.into_iter().chain(std::iter::once(encoder.finish())));
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        self.renderer.render(render_pass, &self.paint_jobs, &self.screen);
+    }
+}
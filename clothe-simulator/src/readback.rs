@@ -0,0 +1,33 @@
+use wgpu_bootstrap::wgpu;
+
+/// Copy the first `count` elements of a GPU buffer back to the CPU. The
+/// buffer needs the `COPY_SRC` usage. Blocks until the copy is done.
+pub fn read_buffer<T: bytemuck::Pod>(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    buffer: &wgpu::Buffer,
+    count: usize,
+) -> Vec<T> {
+    let size = (count * std::mem::size_of::<T>()) as wgpu::BufferAddress;
+    let staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback Buffer"),
+        size,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Readback Encoder"),
+    });
+    encoder.copy_buffer_to_buffer(buffer, 0, &staging_buffer, 0, size);
+    queue.submit(std::iter::once(encoder.finish()));
+
+    let slice = staging_buffer.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |_| ());
+    device.poll(wgpu::Maintain::Wait);
+
+    let data = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+    staging_buffer.unmap();
+
+    data
+}
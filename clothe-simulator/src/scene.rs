@@ -0,0 +1,739 @@
+use std::{fmt, fs, io, path::{Path, PathBuf}};
+
+use bytemuck::Zeroable;
+use serde::{Deserialize, Serialize};
+use wgpu_bootstrap::wgpu;
+
+use crate::{
+    animation::SphereAnimation,
+    clothe::{Clothe, ClotheError, PinSpec},
+    data_containers::{Capsule, ClotheData, Colliders, ComputeData, OrientedBox, Plane, SelfCollisionData,
+        Sphere, WindData},
+    import::read_obj,
+    integrator::{Integrator, Solver},
+};
+
+// Frame duration the substeps of `simulation.iterations` divide, in seconds
+const REFERENCE_FRAME_TIME: f32 = 1.0 / 60.0;
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(String, io::Error),
+    Parse(String, toml::de::Error),
+    Invalid(String),
+    Serialize(toml::ser::Error),
+    Write(String, io::Error),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(path, error) => write!(f, "cannot read scene file `{}`: {}", path, error),
+            SceneError::Parse(path, error) => write!(f, "invalid scene file `{}`: {}", path, error),
+            SceneError::Invalid(message) => write!(f, "invalid scene: {}", message),
+            SceneError::Serialize(error) => write!(f, "cannot serialize the scene: {}", error),
+            SceneError::Write(path, error) => write!(f, "cannot write scene file `{}`: {}", path, error),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClotheConfig {
+    pub size: f32,
+    pub number_squares: u32,
+    pub center: [f32; 3],
+    pub mass: f32, // Of each vertex, in kg
+    // Areal density of the fabric in g/m², replaces `mass`
+    pub density: Option<f32>,
+    pub pins: PinSpec,
+    // Triangulated OBJ used instead of the square grid, relative to the scene file
+    pub mesh: Option<PathBuf>,
+}
+
+impl Default for ClotheConfig {
+    fn default() -> Self {
+        Self {
+            size: 2.5,
+            number_squares: 100,
+            center: [0.0, 2.0, 0.0],
+            mass: 1.0,
+            density: None,
+            pins: PinSpec::None,
+            mesh: None,
+        }
+    }
+}
+
+// One factor for each kind of spring
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpringFactors {
+    pub structural: f32,
+    pub shear: f32,
+    pub bend: f32,
+}
+
+impl Default for SpringFactors {
+    fn default() -> Self {
+        Self {
+            structural: 1.0,
+            shear: 1.0,
+            bend: 1.0,
+        }
+    }
+}
+
+// One factor for each direction of the fabric
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DirectionFactors {
+    pub warp: f32,
+    pub weft: f32,
+    pub bias: f32,
+}
+
+impl Default for DirectionFactors {
+    fn default() -> Self {
+        Self {
+            warp: 1.0,
+            weft: 1.0,
+            bias: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MaterialConfig {
+    pub tear_strain: f32,
+    pub stiffness: SpringFactors,
+    pub damping: SpringFactors,
+    pub stretch: DirectionFactors,
+    pub bend: DirectionFactors,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SimulationConfig {
+    pub spring_constant: f32,
+    pub damping_factor: f32,
+    pub gravity: f32,
+    pub iterations: u32,
+    pub time_step: Option<f32>,
+    pub max_frame_time: f32,
+    pub interpolate: bool,
+    pub integrator: Integrator,
+    pub solver: Solver,
+    pub compliance: f32,
+    pub constraint_iterations: u32,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            spring_constant: 1200000.0,
+            damping_factor: 0.8,
+            gravity: -9.81,
+            iterations: 150,
+            time_step: None,
+            max_frame_time: 0.1,
+            interpolate: false,
+            integrator: Integrator::SymplecticEuler,
+            solver: Solver::MassSpring,
+            compliance: 0.0,
+            constraint_iterations: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SphereConfig {
+    pub center: [f32; 3],
+    pub radius: f32,
+    pub friction_factor: f32,
+    pub animation: Option<SphereAnimation>,
+}
+
+impl Default for SphereConfig {
+    fn default() -> Self {
+        Self {
+            center: [0.0, 0.0, 0.0],
+            radius: 1.05,
+            friction_factor: 0.1,
+            animation: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BoxConfig {
+    pub center: [f32; 3],
+    pub half_extents: [f32; 3],
+    pub rotation_axis: [f32; 3],
+    pub rotation_angle: f32, // Degrees
+    pub friction_factor: f32,
+}
+
+impl Default for BoxConfig {
+    fn default() -> Self {
+        Self {
+            center: [0.0, 0.0, 0.0],
+            half_extents: [0.5, 0.5, 0.5],
+            rotation_axis: [0.0, 1.0, 0.0],
+            rotation_angle: 0.0,
+            friction_factor: 0.1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CapsuleConfig {
+    pub start: [f32; 3],
+    pub end: [f32; 3],
+    pub radius: f32,
+    pub friction_factor: f32,
+}
+
+impl Default for CapsuleConfig {
+    fn default() -> Self {
+        Self {
+            start: [-0.5, 0.0, 0.0],
+            end: [0.5, 0.0, 0.0],
+            radius: 0.25,
+            friction_factor: 0.1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PlaneConfig {
+    pub normal: [f32; 3],
+    pub offset: f32,
+    pub friction_factor: f32,
+    pub size: f32,
+}
+
+impl Default for PlaneConfig {
+    fn default() -> Self {
+        Self {
+            normal: [0.0, 1.0, 0.0],
+            offset: -1.0,
+            friction_factor: 0.5,
+            size: 10.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SelfCollisionConfig {
+    pub thickness: f32,
+    pub bucket_size: u32,
+}
+
+impl Default for SelfCollisionConfig {
+    fn default() -> Self {
+        Self {
+            thickness: 0.02,
+            bucket_size: 8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindConfig {
+    pub velocity: [f32; 3],
+    pub turbulence: f32,
+    pub drag_coefficient: f32,
+    pub lift_coefficient: f32,
+    pub air_density: f32,
+}
+
+impl Default for WindConfig {
+    fn default() -> Self {
+        Self {
+            velocity: [0.0, 0.0, 5.0],
+            turbulence: 0.0,
+            drag_coefficient: 1.0,
+            lift_coefficient: 0.5,
+            air_density: 1.225,
+        }
+    }
+}
+
+/// Every tuning parameter of the simulation. Missing sections or keys keep
+/// their default value, unknown keys are rejected.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scene {
+    pub clothe: ClotheConfig,
+    pub material: MaterialConfig,
+    pub simulation: SimulationConfig,
+    pub spheres: Vec<SphereConfig>,
+    pub boxes: Vec<BoxConfig>,
+    pub capsules: Vec<CapsuleConfig>,
+    pub plane: Option<PlaneConfig>,
+    pub self_collision: Option<SelfCollisionConfig>,
+    pub wind: Option<WindConfig>,
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self {
+            clothe: ClotheConfig::default(),
+            material: MaterialConfig::default(),
+            simulation: SimulationConfig::default(),
+            spheres: vec![SphereConfig::default()],
+            boxes: Vec::new(),
+            capsules: Vec::new(),
+            plane: None,
+            self_collision: None,
+            wind: None,
+        }
+    }
+}
+
+fn check(condition: bool, message: &str) -> Result<(), SceneError> {
+    if condition {
+        Ok(())
+    } else {
+        Err(SceneError::Invalid(message.to_string()))
+    }
+}
+
+fn check_finite(values: &[f32], name: &str) -> Result<(), SceneError> {
+    check(values.iter().all(|value| value.is_finite()), &format!("`{}` must be finite", name))
+}
+
+// The values are f32, write them as such rather than with the noise of their
+// f64 conversion (0.8 instead of 0.800000011920929)
+fn round_floats(value: &mut toml::Value) {
+    match value {
+        toml::Value::Float(float) => *float = (*float as f32).to_string().parse().unwrap_or(*float),
+        toml::Value::Array(array) => array.iter_mut().for_each(round_floats),
+        toml::Value::Table(table) => table.iter_mut().for_each(|(_, value)| round_floats(value)),
+        _ => {}
+    }
+}
+
+impl Scene {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SceneError> {
+        let name = path.as_ref().display().to_string();
+        let content = fs::read_to_string(&path).map_err(|e| SceneError::Io(name.clone(), e))?;
+
+        let mut scene = Self::parse_named(&content, &name)?;
+        if let (Some(mesh), Some(directory)) = (&mut scene.clothe.mesh, path.as_ref().parent()) {
+            *mesh = directory.join(&*mesh);
+        }
+        Ok(scene)
+    }
+
+    /// Writes the scene as TOML, the mesh path is made relative to the new file
+    /// when possible.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SceneError> {
+        let name = path.as_ref().display().to_string();
+
+        let mut scene = self.clone();
+        if let (Some(mesh), Some(directory)) = (&mut scene.clothe.mesh, path.as_ref().parent()) {
+            if let Ok(relative) = mesh.strip_prefix(directory) {
+                *mesh = relative.to_path_buf();
+            } else if let Ok(absolute) = mesh.canonicalize() {
+                *mesh = absolute;
+            }
+        }
+
+        let mut value = toml::Value::try_from(&scene).map_err(SceneError::Serialize)?;
+        round_floats(&mut value);
+        let content = toml::to_string_pretty(&value).map_err(SceneError::Serialize)?;
+        fs::write(&path, content).map_err(|e| SceneError::Write(name, e))
+    }
+
+    pub fn parse(content: &str) -> Result<Self, SceneError> {
+        Self::parse_named(content, "<string>")
+    }
+
+    fn parse_named(content: &str, name: &str) -> Result<Self, SceneError> {
+        let scene: Scene = toml::from_str(content).map_err(|e| SceneError::Parse(name.to_string(), e))?;
+
+        scene.validate()?;
+        Ok(scene)
+    }
+
+    pub fn validate(&self) -> Result<(), SceneError> {
+        let clothe = &self.clothe;
+        check_finite(&[clothe.size], "clothe.size")?;
+        check_finite(&clothe.center, "clothe.center")?;
+        check_finite(&[clothe.mass], "clothe.mass")?;
+        check(clothe.size > 0.0, "`clothe.size` must be greater than 0")?;
+        check(clothe.number_squares > 0, "`clothe.number_squares` must be at least 1")?;
+        Clothe::check_size(clothe.number_squares).map_err(|error| SceneError::Invalid(error.to_string()))?;
+        check(clothe.mass > 0.0, "`clothe.mass` must be greater than 0")?;
+        if let Some(density) = clothe.density {
+            check_finite(&[density], "clothe.density")?;
+            check(density > 0.0, "`clothe.density` must be greater than 0")?;
+        }
+        if let (PinSpec::Indices(indices), None) = (&clothe.pins, &clothe.mesh) {
+            let nb_vertices = (clothe.number_squares as u64 + 1).pow(2);
+            check(indices.iter().all(|&i| (i as u64) < nb_vertices),
+                &format!("`clothe.pins` indices must be lower than {}", nb_vertices))?;
+        }
+
+        let material = &self.material;
+        check_finite(&[material.tear_strain], "material.tear_strain")?;
+        check(material.tear_strain >= 0.0, "`material.tear_strain` must be positive")?;
+        for (factors, name) in [(&material.stiffness, "material.stiffness"), (&material.damping, "material.damping")] {
+            let values = [factors.structural, factors.shear, factors.bend];
+            check_finite(&values, name)?;
+            check(values.iter().all(|&value| value >= 0.0), &format!("`{}` must be positive", name))?;
+        }
+        for (factors, name) in [(&material.stretch, "material.stretch"), (&material.bend, "material.bend")] {
+            let values = [factors.warp, factors.weft, factors.bias];
+            check_finite(&values, name)?;
+            check(values.iter().all(|&value| value >= 0.0), &format!("`{}` must be positive", name))?;
+        }
+
+        let simulation = &self.simulation;
+        check_finite(&[simulation.spring_constant], "simulation.spring_constant")?;
+        check_finite(&[simulation.damping_factor], "simulation.damping_factor")?;
+        check_finite(&[simulation.gravity], "simulation.gravity")?;
+        check(simulation.spring_constant > 0.0, "`simulation.spring_constant` must be greater than 0")?;
+        check(simulation.damping_factor >= 0.0, "`simulation.damping_factor` must be positive")?;
+        check(simulation.iterations > 0, "`simulation.iterations` must be at least 1")?;
+        if let Some(time_step) = simulation.time_step {
+            check_finite(&[time_step], "simulation.time_step")?;
+            check(time_step > 0.0, "`simulation.time_step` must be greater than 0")?;
+        }
+        check_finite(&[simulation.max_frame_time], "simulation.max_frame_time")?;
+        check(simulation.max_frame_time > 0.0, "`simulation.max_frame_time` must be greater than 0")?;
+        check_finite(&[simulation.compliance], "simulation.compliance")?;
+        check(simulation.compliance >= 0.0, "`simulation.compliance` must be positive")?;
+        check(
+            simulation.constraint_iterations > 0,
+            "`simulation.constraint_iterations` must be at least 1",
+        )?;
+
+        for (i, sphere) in self.spheres.iter().enumerate() {
+            let name = |key: &str| format!("spheres[{}].{}", i, key);
+            check_finite(&sphere.center, &name("center"))?;
+            check_finite(&[sphere.radius], &name("radius"))?;
+            check_finite(&[sphere.friction_factor], &name("friction_factor"))?;
+            check(sphere.radius > 0.0, &format!("`{}` must be greater than 0", name("radius")))?;
+            check(sphere.friction_factor >= 0.0, &format!("`{}` must be positive", name("friction_factor")))?;
+            match &sphere.animation {
+                Some(SphereAnimation::Orbit { radius, period }) => {
+                    check_finite(&[*radius, *period], &name("animation"))?;
+                    check(*period > 0.0, &format!("`{}` must be greater than 0", name("animation.period")))?;
+                }
+                Some(SphereAnimation::Bounce { height, period }) => {
+                    check_finite(&[*height, *period], &name("animation"))?;
+                    check(*period > 0.0, &format!("`{}` must be greater than 0", name("animation.period")))?;
+                }
+                Some(SphereAnimation::Keyframes { keyframes, .. }) => {
+                    check(!keyframes.is_empty(), &format!("`{}` must not be empty", name("animation.keyframes")))?;
+                    for keyframe in keyframes {
+                        check_finite(&[keyframe.time], &name("animation.keyframes.time"))?;
+                        check_finite(&keyframe.offset, &name("animation.keyframes.offset"))?;
+                    }
+                    check(keyframes.windows(2).all(|pair| pair[0].time < pair[1].time),
+                        &format!("`{}` must be sorted by time", name("animation.keyframes")))?;
+                }
+                Some(SphereAnimation::Keyboard { speed }) => {
+                    check_finite(&[*speed], &name("animation.speed"))?;
+                    check(*speed >= 0.0, &format!("`{}` must be positive", name("animation.speed")))?;
+                }
+                None => {}
+            }
+        }
+
+        for (i, b) in self.boxes.iter().enumerate() {
+            let name = |key: &str| format!("boxes[{}].{}", i, key);
+            check_finite(&b.center, &name("center"))?;
+            check_finite(&b.half_extents, &name("half_extents"))?;
+            check_finite(&b.rotation_axis, &name("rotation_axis"))?;
+            check_finite(&[b.rotation_angle], &name("rotation_angle"))?;
+            check_finite(&[b.friction_factor], &name("friction_factor"))?;
+            check(b.half_extents.iter().all(|&h| h > 0.0),
+                &format!("`{}` must be greater than 0", name("half_extents")))?;
+            check(b.rotation_axis.iter().any(|&a| a != 0.0), &format!("`{}` must not be null", name("rotation_axis")))?;
+            check(b.friction_factor >= 0.0, &format!("`{}` must be positive", name("friction_factor")))?;
+        }
+
+        for (i, capsule) in self.capsules.iter().enumerate() {
+            let name = |key: &str| format!("capsules[{}].{}", i, key);
+            check_finite(&capsule.start, &name("start"))?;
+            check_finite(&capsule.end, &name("end"))?;
+            check_finite(&[capsule.radius], &name("radius"))?;
+            check_finite(&[capsule.friction_factor], &name("friction_factor"))?;
+            check(capsule.radius > 0.0, &format!("`{}` must be greater than 0", name("radius")))?;
+            check(capsule.friction_factor >= 0.0, &format!("`{}` must be positive", name("friction_factor")))?;
+        }
+
+        if let Some(plane) = &self.plane {
+            check_finite(&plane.normal, "plane.normal")?;
+            check_finite(&[plane.offset], "plane.offset")?;
+            check_finite(&[plane.friction_factor], "plane.friction_factor")?;
+            check_finite(&[plane.size], "plane.size")?;
+            check(plane.normal.iter().any(|&n| n != 0.0), "`plane.normal` must not be null")?;
+            check(plane.friction_factor >= 0.0, "`plane.friction_factor` must be positive")?;
+            check(plane.size > 0.0, "`plane.size` must be greater than 0")?;
+        }
+
+        if let Some(self_collision) = &self.self_collision {
+            check_finite(&[self_collision.thickness], "self_collision.thickness")?;
+            check(self_collision.thickness > 0.0, "`self_collision.thickness` must be greater than 0")?;
+            check(self_collision.bucket_size > 0, "`self_collision.bucket_size` must be at least 1")?;
+        }
+
+        if let Some(wind) = &self.wind {
+            check_finite(&wind.velocity, "wind.velocity")?;
+            check_finite(
+                &[wind.turbulence, wind.drag_coefficient, wind.lift_coefficient, wind.air_density],
+                "wind",
+            )?;
+            check(wind.turbulence >= 0.0, "`wind.turbulence` must be positive")?;
+            check(wind.air_density >= 0.0, "`wind.air_density` must be positive")?;
+        }
+
+        Ok(())
+    }
+
+    pub fn build_clothe(&self) -> Result<Clothe, ClotheError> {
+        self.build_checked_clothe(None)
+    }
+
+    /// Same as `build_clothe`, the buffers are checked against the device
+    /// limits before the clothe is built.
+    pub fn build_gpu_clothe(&self, limits: &wgpu::Limits) -> Result<Clothe, ClotheError> {
+        self.build_checked_clothe(Some(limits))
+    }
+
+    fn build_checked_clothe(&self, limits: Option<&wgpu::Limits>) -> Result<Clothe, ClotheError> {
+        let mut clothe = match &self.clothe.mesh {
+            Some(path) => {
+                let mesh = read_obj(path)?;
+                if let Some(limits) = limits {
+                    Clothe::check_mesh_buffers(&mesh, limits)?;
+                }
+                Clothe::from_mesh(&mesh, &self.clothe.center, &self.clothe.pins)?
+            }
+            None => {
+                if let Some(limits) = limits {
+                    Clothe::check_grid_buffers(self.clothe.number_squares, limits)?;
+                }
+                Clothe::new(self.clothe.size, self.clothe.number_squares, &self.clothe.center, &self.clothe.pins)?
+            }
+        };
+
+        match self.clothe.density {
+            Some(density) => clothe.set_areal_density(density),
+            None => clothe.set_mass(self.clothe.mass),
+        }
+        Ok(clothe)
+    }
+
+    pub fn spheres(&self) -> Vec<Sphere> {
+        self.spheres
+            .iter()
+            .map(|sphere| Sphere {
+                x: sphere.center[0],
+                y: sphere.center[1],
+                z: sphere.center[2],
+                radius: sphere.radius,
+                friction_factor: sphere.friction_factor,
+                velocity_x: 0.0,
+                velocity_y: 0.0,
+                velocity_z: 0.0,
+            })
+            .collect()
+    }
+
+    pub fn boxes(&self) -> Vec<OrientedBox> {
+        self.boxes
+            .iter()
+            .map(|b| {
+                let [x, y, z] = b.rotation_axis;
+                let norm = (x * x + y * y + z * z).sqrt();
+                let (sin, cos) = (b.rotation_angle.to_radians() / 2.0).sin_cos();
+
+                OrientedBox {
+                    x: b.center[0],
+                    y: b.center[1],
+                    z: b.center[2],
+                    half_x: b.half_extents[0],
+                    half_y: b.half_extents[1],
+                    half_z: b.half_extents[2],
+                    rotation_x: x / norm * sin,
+                    rotation_y: y / norm * sin,
+                    rotation_z: z / norm * sin,
+                    rotation_w: cos,
+                    friction_factor: b.friction_factor,
+                }
+            })
+            .collect()
+    }
+
+    pub fn capsules(&self) -> Vec<Capsule> {
+        self.capsules
+            .iter()
+            .map(|capsule| Capsule {
+                start_x: capsule.start[0],
+                start_y: capsule.start[1],
+                start_z: capsule.start[2],
+                end_x: capsule.end[0],
+                end_y: capsule.end[1],
+                end_z: capsule.end[2],
+                radius: capsule.radius,
+                friction_factor: capsule.friction_factor,
+            })
+            .collect()
+    }
+
+    pub fn colliders(&self) -> Colliders {
+        Colliders {
+            spheres: self.spheres(),
+            boxes: self.boxes(),
+            capsules: self.capsules(),
+            plane: self.plane(),
+        }
+    }
+
+    pub fn plane(&self) -> Plane {
+        match &self.plane {
+            Some(plane) => {
+                let [x, y, z] = plane.normal;
+                let norm = (x * x + y * y + z * z).sqrt();
+
+                Plane {
+                    normal_x: x / norm,
+                    normal_y: y / norm,
+                    normal_z: z / norm,
+                    offset: plane.offset,
+                    friction_factor: plane.friction_factor,
+                    enabled: 1,
+                }
+            }
+            None => Plane::zeroed(),
+        }
+    }
+
+    pub fn self_collision_data(&self, clothe: &Clothe) -> Option<SelfCollisionData> {
+        self.self_collision.as_ref().map(|config| SelfCollisionData {
+            thickness: config.thickness,
+            // About two buckets per vertex to keep the hash collisions low
+            table_size: (2 * clothe.nb_vertices).next_power_of_two(),
+            bucket_size: config.bucket_size,
+            nb_vertices: clothe.nb_vertices,
+        })
+    }
+
+    /// Wind at time 0, the time is then advanced by the application.
+    pub fn wind_data(&self, nb_triangles: u32) -> Option<WindData> {
+        self.wind.as_ref().map(|config| WindData {
+            velocity_x: config.velocity[0],
+            velocity_y: config.velocity[1],
+            velocity_z: config.velocity[2],
+            turbulence: config.turbulence,
+            drag_coefficient: config.drag_coefficient,
+            lift_coefficient: config.lift_coefficient,
+            air_density: config.air_density,
+            time: 0.0,
+            nb_triangles,
+        })
+    }
+
+    pub fn clothe_data(&self, clothe: &Clothe) -> ClotheData {
+        ClotheData {
+            center_x: clothe.center_x,
+            center_y: clothe.center_y,
+            center_z: clothe.center_z,
+            nb_vertices: clothe.nb_vertices,
+            tear_strain: self.material.tear_strain,
+        }
+    }
+
+    // Duration of a substep, `iterations` substeps per 60 Hz frame unless set
+    pub fn time_step(&self) -> f32 {
+        self.simulation.time_step.unwrap_or(REFERENCE_FRAME_TIME / self.simulation.iterations as f32)
+    }
+
+    pub fn compute_data(&self, delta_time: f32) -> ComputeData {
+        ComputeData {
+            spring_contant: self.simulation.spring_constant,
+            damping_factor: self.simulation.damping_factor,
+            gravity: self.simulation.gravity,
+            delta_time,
+            nb_spheres: self.spheres.len() as u32,
+            nb_boxes: self.boxes.len() as u32,
+            nb_capsules: self.capsules.len() as u32,
+            compliance: self.simulation.compliance,
+            structural_stiffness: self.material.stiffness.structural,
+            shear_stiffness: self.material.stiffness.shear,
+            bend_stiffness: self.material.stiffness.bend,
+            structural_damping: self.material.damping.structural,
+            shear_damping: self.material.damping.shear,
+            bend_damping: self.material.damping.bend,
+            warp_stretch: self.material.stretch.warp,
+            weft_stretch: self.material.stretch.weft,
+            bias_stretch: self.material.stretch.bias,
+            warp_bend: self.material.bend.warp,
+            weft_bend: self.material.bend.weft,
+            bias_bend: self.material.bend.bias,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_file_gives_the_default_scene() {
+        let scene = Scene::parse("").unwrap();
+        assert_eq!(format!("{:?}", scene), format!("{:?}", Scene::default()));
+        assert_eq!(scene.time_step(), REFERENCE_FRAME_TIME / 150.0);
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        for content in ["unknown = 1", "[clothe]\nsize = 2.0\nsquares = 10", "[simulation]\nintegrator = \"euler\""] {
+            assert!(matches!(Scene::parse(content), Err(SceneError::Parse(..))), "{}", content);
+        }
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let mut scene = Scene::default();
+        scene.simulation.gravity = f32::NAN;
+        assert!(matches!(scene.validate(), Err(SceneError::Invalid(_))));
+
+        for content in ["[clothe]\nsize = -1.0", "[simulation]\ntime_step = 0.0", "[clothe]\ncenter = [0.0, inf, 0.0]"] {
+            assert!(matches!(Scene::parse(content), Err(SceneError::Invalid(_))), "{}", content);
+        }
+    }
+
+    #[test]
+    fn saved_scene_parses_back_to_the_same_values() {
+        let mut scene = Scene::default();
+        scene.simulation.damping_factor = 0.3;
+        scene.simulation.time_step = Some(1e-4);
+        scene.clothe.pins = PinSpec::Indices(vec![0, 7]);
+        scene.spheres[0].center = [0.1, -0.2, 0.3];
+
+        let path = std::env::temp_dir().join(format!("clothe-scene-{}.toml", std::process::id()));
+        scene.save(&path).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        // The values are written as f32, without the f64 conversion noise
+        assert!(content.contains("damping_factor = 0.3\n"), "{}", content);
+        assert_eq!(format!("{:?}", Scene::parse(&content).unwrap()), format!("{:?}", scene));
+    }
+}
@@ -0,0 +1,75 @@
+use wgpu_bootstrap::wgpu;
+
+use crate::{
+    data_containers::SelfCollisionData,
+    gpu::{ComputeStage, GpuContext},
+};
+
+/// GPU self collision stage: each substep, the vertices are sorted into a
+/// uniform grid spatial hash and the vertices closer than the thickness are
+/// pushed apart.
+pub struct SelfCollision {
+    stages: Vec<ComputeStage>,
+}
+
+impl SelfCollision {
+    pub fn new(
+        context: &impl GpuContext,
+        vertex_buffer: &wgpu::Buffer,
+        spring_buffer: &wgpu::Buffer,
+        spring_offset_buffer: &wgpu::Buffer,
+        data: SelfCollisionData,
+    ) -> Self {
+        let source = include_str!("shaders/self_collision.wgsl");
+
+        let data_buffer = context.create_buffer(&[data], wgpu::BufferUsages::UNIFORM);
+        let cell_count_buffer = context.create_buffer(
+            &vec![0u32; data.table_size as usize],
+            wgpu::BufferUsages::STORAGE,
+        );
+        let cell_entry_buffer = context.create_buffer(
+            &vec![0u32; (data.table_size * data.bucket_size) as usize],
+            wgpu::BufferUsages::STORAGE,
+        );
+        let correction_buffer = context.create_buffer(
+            &vec![[0.0f32; 4]; data.nb_vertices as usize],
+            wgpu::BufferUsages::STORAGE,
+        );
+
+        let uniforms: &[(u32, &wgpu::Buffer)] = &[(0, &data_buffer)];
+        let stage = |entry_point: &str, buffers: &[(u32, &wgpu::Buffer)], nb_invocations: u32| {
+            ComputeStage::new(context, "Self Collision", source, entry_point, [buffers, uniforms],
+                nb_invocations)
+        };
+
+        let stages = vec![
+            stage("clear_cells", &[(2, &cell_count_buffer)], data.table_size),
+            stage(
+                "insert_vertices",
+                &[(0, vertex_buffer), (2, &cell_count_buffer), (3, &cell_entry_buffer)],
+                data.nb_vertices,
+            ),
+            stage(
+                "collide",
+                &[
+                    (0, vertex_buffer),
+                    (1, spring_buffer),
+                    (2, &cell_count_buffer),
+                    (3, &cell_entry_buffer),
+                    (4, &correction_buffer),
+                    (5, spring_offset_buffer),
+                ],
+                data.nb_vertices,
+            ),
+            stage("apply_corrections", &[(0, vertex_buffer), (4, &correction_buffer)], data.nb_vertices),
+        ];
+
+        Self { stages }
+    }
+
+    pub fn dispatch<'a>(&'a self, compute_pass: &mut wgpu::ComputePass<'a>) {
+        for stage in &self.stages {
+            stage.dispatch(compute_pass);
+        }
+    }
+}
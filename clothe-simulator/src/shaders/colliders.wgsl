@@ -0,0 +1,168 @@
+// Collider definitions shared by the compute shaders, prepended to them at
+// pipeline creation
+
+struct Sphere {
+    x: f32,
+    y: f32,
+    z: f32,
+    radius: f32,
+    friction_factor: f32,
+    velocity_x: f32,
+    velocity_y: f32,
+    velocity_z: f32,
+}
+
+struct Plane {
+    normal_x: f32,
+    normal_y: f32,
+    normal_z: f32,
+    offset: f32,
+    friction_factor: f32,
+    enabled: u32,
+}
+
+struct OrientedBox {
+    x: f32,
+    y: f32,
+    z: f32,
+    half_x: f32,
+    half_y: f32,
+    half_z: f32,
+    rotation_x: f32,
+    rotation_y: f32,
+    rotation_z: f32,
+    rotation_w: f32,
+    friction_factor: f32,
+}
+
+struct Capsule {
+    start_x: f32,
+    start_y: f32,
+    start_z: f32,
+    end_x: f32,
+    end_y: f32,
+    end_z: f32,
+    radius: f32,
+    friction_factor: f32,
+}
+
+// Rotate `v` by the unit quaternion `q`
+fn quat_rotate(q: vec4<f32>, v: vec3<f32>) -> vec3<f32> {
+    let t = 2.0 * cross(q.xyz, v);
+    return v + q.w * t + cross(q.xyz, t);
+}
+
+fn quat_conjugate(q: vec4<f32>) -> vec4<f32> {
+    return vec4(-q.xyz, q.w);
+}
+
+fn sphere_sdf(sphere: Sphere, p: vec3<f32>) -> f32 {
+    return distance(vec3(sphere.x, sphere.y, sphere.z), p) - sphere.radius;
+}
+
+fn sphere_velocity(sphere: Sphere) -> vec3<f32> {
+    return vec3(sphere.velocity_x, sphere.velocity_y, sphere.velocity_z);
+}
+
+fn sphere_closest_point(sphere: Sphere, p: vec3<f32>) -> vec3<f32> {
+    let center = vec3(sphere.x, sphere.y, sphere.z);
+    return center + sphere.radius * normalize(p - center);
+}
+
+// Outward normal of the surface closest to the point
+fn sphere_normal(sphere: Sphere, p: vec3<f32>) -> vec3<f32> {
+    return normalize(p - vec3(sphere.x, sphere.y, sphere.z));
+}
+
+fn plane_normal(plane: Plane) -> vec3<f32> {
+    return vec3(plane.normal_x, plane.normal_y, plane.normal_z);
+}
+
+fn plane_sdf(plane: Plane, p: vec3<f32>) -> f32 {
+    return dot(plane_normal(plane), p) - plane.offset;
+}
+
+fn plane_closest_point(plane: Plane, p: vec3<f32>) -> vec3<f32> {
+    return p - plane_sdf(plane, p) * plane_normal(plane);
+}
+
+fn box_rotation(b: OrientedBox) -> vec4<f32> {
+    return vec4(b.rotation_x, b.rotation_y, b.rotation_z, b.rotation_w);
+}
+
+fn box_sdf(b: OrientedBox, p: vec3<f32>) -> f32 {
+    let local = quat_rotate(quat_conjugate(box_rotation(b)), p - vec3(b.x, b.y, b.z));
+    let q = abs(local) - vec3(b.half_x, b.half_y, b.half_z);
+
+    return length(max(q, vec3(0.0))) + min(max(q.x, max(q.y, q.z)), 0.0);
+}
+
+// Closest point on the surface of the box, inside points go to the nearest face
+fn box_closest_point(b: OrientedBox, p: vec3<f32>) -> vec3<f32> {
+    let rotation = box_rotation(b);
+    let center = vec3(b.x, b.y, b.z);
+    let half = vec3(b.half_x, b.half_y, b.half_z);
+    var local = quat_rotate(quat_conjugate(rotation), p - center);
+
+    let clamped = clamp(local, -half, half);
+    if any(clamped != local) {
+        return center + quat_rotate(rotation, clamped);
+    }
+
+    let gap = half - abs(local);
+    let side = select(vec3(-1.0), vec3(1.0), local >= vec3(0.0));
+    if gap.x <= gap.y && gap.x <= gap.z {
+        local.x = side.x * half.x;
+    } else if gap.y <= gap.z {
+        local.y = side.y * half.y;
+    } else {
+        local.z = side.z * half.z;
+    }
+
+    return center + quat_rotate(rotation, local);
+}
+
+fn box_normal(b: OrientedBox, p: vec3<f32>) -> vec3<f32> {
+    let rotation = box_rotation(b);
+    let half = vec3(b.half_x, b.half_y, b.half_z);
+    let local = quat_rotate(quat_conjugate(rotation), p - vec3(b.x, b.y, b.z));
+
+    let clamped = clamp(local, -half, half);
+    if any(clamped != local) {
+        return quat_rotate(rotation, normalize(local - clamped));
+    }
+
+    // Inside points take the normal of the nearest face
+    let gap = half - abs(local);
+    let side = select(vec3(-1.0), vec3(1.0), local >= vec3(0.0));
+    var normal = vec3(0.0, 0.0, side.z);
+    if gap.x <= gap.y && gap.x <= gap.z {
+        normal = vec3(side.x, 0.0, 0.0);
+    } else if gap.y <= gap.z {
+        normal = vec3(0.0, side.y, 0.0);
+    }
+
+    return quat_rotate(rotation, normal);
+}
+
+fn capsule_segment_point(capsule: Capsule, p: vec3<f32>) -> vec3<f32> {
+    let start = vec3(capsule.start_x, capsule.start_y, capsule.start_z);
+    let end = vec3(capsule.end_x, capsule.end_y, capsule.end_z);
+    let axis = end - start;
+    let t = clamp(dot(p - start, axis) / max(dot(axis, axis), 1e-12), 0.0, 1.0);
+
+    return start + t * axis;
+}
+
+fn capsule_sdf(capsule: Capsule, p: vec3<f32>) -> f32 {
+    return distance(capsule_segment_point(capsule, p), p) - capsule.radius;
+}
+
+fn capsule_closest_point(capsule: Capsule, p: vec3<f32>) -> vec3<f32> {
+    let segment_point = capsule_segment_point(capsule, p);
+    return segment_point + capsule.radius * normalize(p - segment_point);
+}
+
+fn capsule_normal(capsule: Capsule, p: vec3<f32>) -> vec3<f32> {
+    return normalize(p - capsule_segment_point(capsule, p));
+}
@@ -1,31 +1,30 @@
+// Prepended with colliders.wgsl and contacts.wgsl
+
 struct Vertex {
     position: vec3<f32>,
     normal: vec3<f32>,
     velocity: vec3<f32>,
     resultant: vec3<f32>,
+    pinned: u32,
     tex_coords: vec3<f32>,
 }
 
-struct Sphere {
-    x: f32,
-    y: f32,
-    z: f32,
-    radius: f32,
-    friction_factor: f32,
-}
-
 struct ClotheData {
     center_x: f32,
     center_y: f32,
     center_z: f32,
     nb_vertices: u32,
-    mass: f32,
+    tear_strain: f32,
 }
 
+// One end of a spring, see `Spring`
 struct Spring {
-    links: array<u32, 12>,
-    rest_distance: array<f32, 12>,
-    current_distance: array<f32, 12>,
+    link: u32,
+    rest_distance: f32,
+    current_distance: f32,
+    lambda: f32,
+    kind: u32,
+    direction: u32,
 }
 
 struct ComputeData {
@@ -33,64 +32,271 @@ struct ComputeData {
     damping_factor: f32,
     gravity: f32,
     delta_time: f32,
+    nb_spheres: u32,
+    nb_boxes: u32,
+    nb_capsules: u32,
+    compliance: f32,
+    structural_stiffness: f32,
+    shear_stiffness: f32,
+    bend_stiffness: f32,
+    structural_damping: f32,
+    shear_damping: f32,
+    bend_damping: f32,
+    warp_stretch: f32,
+    weft_stretch: f32,
+    bias_stretch: f32,
+    warp_bend: f32,
+    weft_bend: f32,
+    bias_bend: f32,
+}
+
+// Memory of the integrators, see `IntegratorState`
+struct IntegratorState {
+    position: vec3<f32>,
+    velocity: vec3<f32>,
+    k_position: vec3<f32>,
+    k_velocity: vec3<f32>,
+    sum_position: vec3<f32>,
+    sum_velocity: vec3<f32>,
 }
 
 @group(0) @binding(0) var<storage, read_write> vertices: array<Vertex>;
-@group(0) @binding(1) var<storage, read> springs: array<Spring>;
-@group(1) @binding(0) var<uniform> sphere: Sphere;
+@group(0) @binding(1) var<storage, read_write> springs: array<Spring>;
+@group(0) @binding(2) var<storage, read_write> states: array<IntegratorState>;
+@group(0) @binding(3) var<storage, read> spring_offsets: array<u32>;
+@group(0) @binding(4) var<storage, read> masses: array<f32>;
+@group(1) @binding(0) var<storage, read> spheres: array<Sphere>;
 @group(1) @binding(1) var<uniform> data: ComputeData;
 @group(1) @binding(2) var<uniform> clothe_data: ClotheData;
+@group(1) @binding(3) var<uniform> plane: Plane;
+@group(1) @binding(4) var<storage, read> boxes: array<OrientedBox>;
+@group(1) @binding(5) var<storage, read> capsules: array<Capsule>;
 
+// Index of `SpringKind::Bend`
+const BEND: u32 = 2u;
 
-@compute @workgroup_size(255, 1, 1) 
-fn main(@builtin(global_invocation_id) param: vec3<u32>) {
-    if (param.x > clothe_data.nb_vertices) {
-        return;
+// Factor of the stiffness for the kind and the direction of a spring, see
+// `SpringKind` and `SpringDirection`
+fn spring_stiffness(spring: Spring) -> f32 {
+    let kind = vec3(data.structural_stiffness, data.shear_stiffness, data.bend_stiffness)[spring.kind];
+    var direction = vec3(data.warp_stretch, data.weft_stretch, data.bias_stretch);
+    if spring.kind == BEND {
+        direction = vec3(data.warp_bend, data.weft_bend, data.bias_bend);
     }
 
-    var sphere_vec = vec3<f32>(sphere.x, sphere.y, sphere.z);
-    var vertex = vertices[param.x];
-    var spring = springs[param.x];
+    return kind * direction[spring.direction];
+}
+
+// Factor of the damping for the kind of a spring
+fn spring_damping(kind: u32) -> f32 {
+    return vec3(data.structural_damping, data.shear_damping, data.bend_damping)[kind];
+}
 
-    // Reset resultant
-    vertices[param.x].resultant = vec3(0.0);
+// Forces applied on the vertex in the given state, the linked vertices are
+// read from the vertex buffer
+fn compute_resultant(index: u32, state: State) -> vec3<f32> {
+    var resultant = vec3(0.0);
+    var damping_sum = 0.0;
+    var nb_springs = 0.0;
 
-    // Loop on every spring
-    for (var i: i32 = 0; i < 12; i++) {
-        let vertex_link = vertices[spring.links[i]];
+    // Loop on every spring of the vertex
+    for (var i = spring_offsets[index]; i < spring_offsets[index + 1u]; i++) {
+        let spring = springs[i];
 
-        // If it is the same spring, don't make useless calculations
-        if spring.links[i] == param.x {
+        // Broken springs link the vertex to itself
+        if spring.link == index {
             continue;
         }
 
-        let norm = (spring.current_distance[i] - spring.rest_distance[i]) * data.spring_contant;
-        let spring_force = (vertex_link.position - vertex.position)*norm;
-    
-        // Calcul resistances
-        vertices[param.x].resultant += spring_force - vertices[param.x].velocity * data.damping_factor;
-        vertices[param.x].resultant[1] += data.gravity * clothe_data.mass;
+        let link_position = vertices[spring.link].position;
+        let norm = (distance(link_position, state.position) - spring.rest_distance) * data.spring_contant
+            * spring_stiffness(spring);
+        let spring_force = (link_position - state.position)*norm;
+
+        resultant += spring_force;
+        damping_sum += spring_damping(spring.kind);
+        nb_springs += 1.0;
+    }
+
+    // Weight and damping are applied once per vertex, the damping is
+    // proportional to the mean factor of its springs
+    var damping = data.damping_factor;
+    if nb_springs > 0.0 {
+        damping *= damping_sum / nb_springs;
     }
+    resultant += -state.velocity * damping;
+    resultant[1] += data.gravity * masses[index];
+
+    // Add friction with the colliders in contact
+    return contact_friction(resultant, state.position);
+}
 
-    // Add friction with the sphere
-    if distance(sphere_vec, vertices[param.x].position) <= sphere.radius {
-        let r_n = dot(vertices[param.x].resultant, vertices[param.x].normal) * vertices[param.x].normal;
-        let r_t = vertices[param.x].resultant - r_n;
+fn vertex_state(index: u32) -> State {
+    return State(vertices[index].position, vertices[index].velocity);
+}
 
-        let one_t = normalize(r_t);
+fn store(index: u32, state: State, resultant: vec3<f32>) {
+    vertices[index].position = state.position;
+    vertices[index].velocity = state.velocity;
+    vertices[index].resultant = resultant;
+}
 
-        vertices[param.x].resultant += -min(length(r_t), sphere.friction_factor*length(r_n))*one_t;
+// Pinned vertices are not integrated
+fn is_fixed(index: u32) -> bool {
+    if vertices[index].pinned != 0u {
+        vertices[index].velocity = vec3(0.0);
+        vertices[index].resultant = vec3(0.0);
+        return true;
     }
+    return false;
+}
+
+@compute @workgroup_size(255, 1, 1)
+fn symplectic_euler(@builtin(global_invocation_id) param: vec3<u32>) {
+    if (param.x >= clothe_data.nb_vertices || is_fixed(param.x)) {
+        return;
+    }
+
+    var state = vertex_state(param.x);
+    let resultant = compute_resultant(param.x, state);
 
     // New velocities and positions
-    vertices[param.x].velocity += vertices[param.x].resultant * data.delta_time / clothe_data.mass;
-    vertices[param.x].position += vertices[param.x].velocity * data.delta_time;
+    state.velocity += resultant * data.delta_time / masses[param.x];
+    state.position += state.velocity * data.delta_time;
+
+    store(param.x, collide(state), resultant);
+}
+
+@compute @workgroup_size(255, 1, 1)
+fn verlet(@builtin(global_invocation_id) param: vec3<u32>) {
+    if (param.x >= clothe_data.nb_vertices) {
+        return;
+    }
+
+    let current = vertex_state(param.x);
+    if is_fixed(param.x) {
+        states[param.x].position = current.position;
+        return;
+    }
+
+    // The velocity only feeds the damping and the colliders
+    let resultant = compute_resultant(param.x, current);
+    let position = 2.0 * current.position - states[param.x].position
+        + resultant * data.delta_time * data.delta_time / masses[param.x];
+    let state = collide(State(position, (position - current.position) / data.delta_time));
+
+    // The previous position follows the velocity given by the colliders
+    states[param.x].position = state.position - state.velocity * data.delta_time;
+    store(param.x, state, resultant);
+}
+
+// Evaluate the derivative of the state currently in the vertex buffer and
+// add it to the RK4 sum with the given weight
+fn rk4_derivative(index: u32, weight: f32) {
+    let state = vertex_state(index);
+    var acceleration = vec3(0.0);
+    if vertices[index].pinned == 0u {
+        acceleration = compute_resultant(index, state) / masses[index];
+    }
+
+    states[index].k_position = state.velocity;
+    states[index].k_velocity = acceleration;
+    states[index].sum_position += weight * state.velocity;
+    states[index].sum_velocity += weight * acceleration;
+}
+
+// Move the vertex to the start of the substep plus `factor` times the last
+// derivative
+fn rk4_advance(index: u32, factor: f32) {
+    if vertices[index].pinned != 0u {
+        return;
+    }
 
-    // Sphere collision
-    if distance(sphere_vec, vertices[param.x].position) < sphere.radius {
-        let old_position = vec3(vertices[param.x].position); 
+    let state = states[index];
+    vertices[index].position = state.position + factor * data.delta_time * state.k_position;
+    vertices[index].velocity = state.velocity + factor * data.delta_time * state.k_velocity;
+}
 
-        vertices[param.x].position = sphere_vec + sphere.radius * normalize(vertices[param.x].position - sphere_vec);
-        vertices[param.x].velocity = (vertices[param.x].position - old_position) / data.delta_time;
+@compute @workgroup_size(255, 1, 1)
+fn rk4_k1(@builtin(global_invocation_id) param: vec3<u32>) {
+    if (param.x >= clothe_data.nb_vertices) {
+        return;
     }
-}
\ No newline at end of file
+
+    let state = vertex_state(param.x);
+    states[param.x].position = state.position;
+    states[param.x].velocity = state.velocity;
+    states[param.x].sum_position = vec3(0.0);
+    states[param.x].sum_velocity = vec3(0.0);
+
+    rk4_derivative(param.x, 1.0);
+}
+
+@compute @workgroup_size(255, 1, 1)
+fn rk4_advance_k1(@builtin(global_invocation_id) param: vec3<u32>) {
+    if (param.x >= clothe_data.nb_vertices) {
+        return;
+    }
+
+    rk4_advance(param.x, 0.5);
+}
+
+@compute @workgroup_size(255, 1, 1)
+fn rk4_k2(@builtin(global_invocation_id) param: vec3<u32>) {
+    if (param.x >= clothe_data.nb_vertices) {
+        return;
+    }
+
+    rk4_derivative(param.x, 2.0);
+}
+
+@compute @workgroup_size(255, 1, 1)
+fn rk4_advance_k2(@builtin(global_invocation_id) param: vec3<u32>) {
+    if (param.x >= clothe_data.nb_vertices) {
+        return;
+    }
+
+    rk4_advance(param.x, 0.5);
+}
+
+@compute @workgroup_size(255, 1, 1)
+fn rk4_k3(@builtin(global_invocation_id) param: vec3<u32>) {
+    if (param.x >= clothe_data.nb_vertices) {
+        return;
+    }
+
+    rk4_derivative(param.x, 2.0);
+}
+
+@compute @workgroup_size(255, 1, 1)
+fn rk4_advance_k3(@builtin(global_invocation_id) param: vec3<u32>) {
+    if (param.x >= clothe_data.nb_vertices) {
+        return;
+    }
+
+    rk4_advance(param.x, 1.0);
+}
+
+@compute @workgroup_size(255, 1, 1)
+fn rk4_k4(@builtin(global_invocation_id) param: vec3<u32>) {
+    if (param.x >= clothe_data.nb_vertices) {
+        return;
+    }
+
+    rk4_derivative(param.x, 1.0);
+}
+
+@compute @workgroup_size(255, 1, 1)
+fn rk4_finish(@builtin(global_invocation_id) param: vec3<u32>) {
+    if (param.x >= clothe_data.nb_vertices || is_fixed(param.x)) {
+        return;
+    }
+
+    let start = states[param.x];
+    let state = State(
+        start.position + data.delta_time / 6.0 * start.sum_position,
+        start.velocity + data.delta_time / 6.0 * start.sum_velocity,
+    );
+
+    store(param.x, collide(state), start.sum_velocity / 6.0 * masses[param.x]);
+}
@@ -0,0 +1,101 @@
+// Contact response shared by the solvers, prepended after colliders.wgsl.
+// The shader must declare the `spheres`, `boxes`, `capsules` and `plane`
+// colliders and the `data` uniform with the collider counts and `delta_time`.
+
+struct State {
+    position: vec3<f32>,
+    velocity: vec3<f32>,
+}
+
+// Remove the tangential part of the resultant, up to the friction limit.
+// `normal` is the normal of the collider surface.
+fn add_friction(resultant: vec3<f32>, normal: vec3<f32>, friction_factor: f32) -> vec3<f32> {
+    let r_n = dot(resultant, normal) * normal;
+    let r_t = resultant - r_n;
+
+    // normalize of a null vector is NaN
+    if length(r_t) == 0.0 {
+        return resultant;
+    }
+    let one_t = normalize(r_t);
+
+    return resultant - min(length(r_t), friction_factor*length(r_n))*one_t;
+}
+
+// Add friction with every collider in contact with the position
+fn contact_friction(start: vec3<f32>, position: vec3<f32>) -> vec3<f32> {
+    var resultant = start;
+
+    for (var s: u32 = 0u; s < data.nb_spheres; s++) {
+        if sphere_sdf(spheres[s], position) <= 0.0 {
+            resultant = add_friction(resultant, sphere_normal(spheres[s], position), spheres[s].friction_factor);
+        }
+    }
+    for (var b: u32 = 0u; b < data.nb_boxes; b++) {
+        if box_sdf(boxes[b], position) <= 0.0 {
+            resultant = add_friction(resultant, box_normal(boxes[b], position), boxes[b].friction_factor);
+        }
+    }
+    for (var c: u32 = 0u; c < data.nb_capsules; c++) {
+        if capsule_sdf(capsules[c], position) <= 0.0 {
+            resultant = add_friction(resultant, capsule_normal(capsules[c], position),
+                capsules[c].friction_factor);
+        }
+    }
+    if plane.enabled != 0u && plane_sdf(plane, position) <= 0.0 {
+        resultant = add_friction(resultant, plane_normal(plane), plane.friction_factor);
+    }
+
+    return resultant;
+}
+
+// Move the vertex on the collider surface
+fn project(state: State, surface_point: vec3<f32>) -> State {
+    return State(surface_point, (surface_point - state.position) / data.delta_time);
+}
+
+// Move the vertex on the surface of a moving collider. As on a still collider
+// the vertex loses its own tangential velocity, then the collider drags it
+// along up to the Coulomb limit of the normal velocity change: a frictionless
+// collider doesn't drag the vertex, a rough one carries it.
+fn project_moving(state: State, surface_point: vec3<f32>, normal: vec3<f32>, velocity: vec3<f32>,
+    friction_factor: f32) -> State {
+    let projected = project(state, surface_point);
+    let tangential = velocity - dot(velocity, normal) * normal;
+    if length(tangential) == 0.0 {
+        return projected;
+    }
+
+    let normal_change = max(dot(projected.velocity - state.velocity, normal), 0.0);
+    let drag = min(friction_factor * normal_change / length(tangential), 1.0);
+
+    return State(projected.position, projected.velocity + drag * tangential);
+}
+
+fn collide(start: State) -> State {
+    var state = start;
+
+    for (var s: u32 = 0u; s < data.nb_spheres; s++) {
+        let sphere = spheres[s];
+        if sphere_sdf(sphere, state.position) < 0.0 {
+            let surface_point = sphere_closest_point(sphere, state.position);
+            let normal = normalize(surface_point - vec3(sphere.x, sphere.y, sphere.z));
+            state = project_moving(state, surface_point, normal, sphere_velocity(sphere), sphere.friction_factor);
+        }
+    }
+    for (var b: u32 = 0u; b < data.nb_boxes; b++) {
+        if box_sdf(boxes[b], state.position) < 0.0 {
+            state = project(state, box_closest_point(boxes[b], state.position));
+        }
+    }
+    for (var c: u32 = 0u; c < data.nb_capsules; c++) {
+        if capsule_sdf(capsules[c], state.position) < 0.0 {
+            state = project(state, capsule_closest_point(capsules[c], state.position));
+        }
+    }
+    if plane.enabled != 0u && plane_sdf(plane, state.position) < 0.0 {
+        state = project(state, plane_closest_point(plane, state.position));
+    }
+
+    return state;
+}
@@ -3,13 +3,18 @@ struct Vertex {
     normal: vec3<f32>,
     velocity: vec3<f32>,
     resultant: vec3<f32>,
+    pinned: u32,
     tex_coords: vec3<f32>,
 }
 
+// One end of a spring, see `Spring`
 struct Spring {
-    links: array<u32, 12>,
-    rest_distance: array<f32, 12>,
-    current_distance: array<f32, 12>,
+    link: u32,
+    rest_distance: f32,
+    current_distance: f32,
+    lambda: f32,
+    kind: u32,
+    direction: u32,
 }
 
 struct ClotheData {
@@ -17,30 +22,38 @@ struct ClotheData {
     center_y: f32,
     center_z: f32,
     nb_vertices: u32,
-    mass: f32,
+    tear_strain: f32,
 }
 
 @group(0) @binding(0) var<storage, read> vertices: array<Vertex>;
 @group(0) @binding(1) var<storage, read_write> springs: array<Spring>;
+@group(0) @binding(2) var<storage, read> spring_offsets: array<u32>;
 @group(1) @binding(0) var<uniform> clothe_data: ClotheData;
 
 @compute @workgroup_size(255, 1, 1) 
 fn main(@builtin(global_invocation_id) param: vec3<u32>) {
-    if (param.x > clothe_data.nb_vertices) {
+    if (param.x >= clothe_data.nb_vertices) {
         return;
     }
 
-    var vertex = vertices[param.x];
-    var spring = springs[param.x];
+    let vertex = vertices[param.x];
 
     // Calcul distance for each spring of the vertex
-    for (var i: i32 = 0; i < 12; i++) {
-        let vertex_link = vertices[spring.links[i]];
+    for (var i = spring_offsets[param.x]; i < spring_offsets[param.x + 1u]; i++) {
+        let spring = springs[i];
 
-        if spring.links[i] == param.x {
+        if spring.link == param.x {
             continue;
         }
 
-        springs[param.x].current_distance[i] =  distance(vertex.position, vertex_link.position);
+        let current_distance = distance(vertex.position, vertices[spring.link].position);
+        springs[i].current_distance = current_distance;
+
+        // Both ends see the same strain, so a spring always breaks on both sides.
+        // A broken spring points to the vertex itself.
+        let strain = current_distance / spring.rest_distance - 1.0;
+        if clothe_data.tear_strain > 0.0 && strain > clothe_data.tear_strain {
+            springs[i].link = param.x;
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,64 @@
+struct Vertex {
+    position: vec3<f32>,
+    normal: vec3<f32>,
+    velocity: vec3<f32>,
+    resultant: vec3<f32>,
+    pinned: u32,
+    tex_coords: vec3<f32>,
+}
+
+struct ComputeData {
+    spring_contant: f32,
+    damping_factor: f32,
+    gravity: f32,
+    delta_time: f32,
+    nb_spheres: u32,
+    nb_boxes: u32,
+    nb_capsules: u32,
+    compliance: f32,
+    structural_stiffness: f32,
+    shear_stiffness: f32,
+    bend_stiffness: f32,
+    structural_damping: f32,
+    shear_damping: f32,
+    bend_damping: f32,
+    warp_stretch: f32,
+    weft_stretch: f32,
+    bias_stretch: f32,
+    warp_bend: f32,
+    weft_bend: f32,
+    bias_bend: f32,
+}
+
+// Vertex dragged with the mouse, see `GrabData`
+struct GrabData {
+    target_x: f32,
+    target_y: f32,
+    target_z: f32,
+    vertex: u32,
+    grabbed: u32,
+    max_speed: f32,
+}
+
+@group(0) @binding(0) var<storage, read_write> vertices: array<Vertex>;
+@group(1) @binding(0) var<uniform> grab: GrabData;
+@group(1) @binding(1) var<uniform> data: ComputeData;
+
+// The grabbed vertex follows the target like a moving pin. Its speed is
+// bounded so a fast drag doesn't overstretch the springs in one substep.
+@compute @workgroup_size(1, 1, 1)
+fn main() {
+    if grab.grabbed == 0u || grab.vertex >= arrayLength(&vertices) {
+        return;
+    }
+
+    let vertex = vertices[grab.vertex];
+    var step = vec3(grab.target_x, grab.target_y, grab.target_z) - vertex.position;
+    let max_step = grab.max_speed * data.delta_time;
+    if length(step) > max_step {
+        step *= max_step / length(step);
+    }
+
+    vertices[grab.vertex].position = vertex.position + step;
+    vertices[grab.vertex].velocity = step / data.delta_time;
+}
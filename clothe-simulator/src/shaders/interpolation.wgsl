@@ -0,0 +1,47 @@
+struct Vertex {
+    position: vec3<f32>,
+    normal: vec3<f32>,
+    velocity: vec3<f32>,
+    resultant: vec3<f32>,
+    pinned: u32,
+    tex_coords: vec3<f32>,
+}
+
+// Blend of the last two substeps drawn, see `InterpolationData`
+struct InterpolationData {
+    alpha: f32,
+    nb_vertices: u32,
+}
+
+@group(0) @binding(0) var<storage, read_write> vertices: array<Vertex>;
+@group(0) @binding(1) var<storage, read_write> previous: array<Vertex>;
+@group(0) @binding(2) var<storage, read_write> rendered: array<Vertex>;
+@group(1) @binding(0) var<uniform> data: InterpolationData;
+
+// Keeps the vertices before the last substep of the frame
+@compute @workgroup_size(255, 1, 1)
+fn snapshot(@builtin(global_invocation_id) param: vec3<u32>) {
+    if param.x >= data.nb_vertices {
+        return;
+    }
+
+    previous[param.x] = vertices[param.x];
+}
+
+// Draws the clothe `alpha` of a substep past the previous state, so the
+// motion stays smooth when the frames don't last a whole number of substeps
+@compute @workgroup_size(255, 1, 1)
+fn blend(@builtin(global_invocation_id) param: vec3<u32>) {
+    if param.x >= data.nb_vertices {
+        return;
+    }
+
+    let before = previous[param.x];
+    var vertex = vertices[param.x];
+    vertex.position = mix(before.position, vertex.position, data.alpha);
+    let normal = mix(before.normal, vertex.normal, data.alpha);
+    if length(normal) > 0.0 {
+        vertex.normal = normalize(normal);
+    }
+    rendered[param.x] = vertex;
+}
@@ -3,55 +3,70 @@ struct Vertex {
     normal: vec3<f32>,
     velocity: vec3<f32>,
     resultant: vec3<f32>,
+    pinned: u32,
     tex_coords: vec3<f32>,
 }
 
+// One end of a spring, see `Spring`
+struct Spring {
+    link: u32,
+    rest_distance: f32,
+    current_distance: f32,
+    lambda: f32,
+    kind: u32,
+    direction: u32,
+}
+
 struct ClotheData {
     center_x: f32,
     center_y: f32,
     center_z: f32,
     nb_vertices: u32,
-    mass: f32,
-}
-
-struct Spring {
-    links: array<u32, 12>,
-    rest_distance: array<f32, 12>,
-    current_distance: array<f32, 12>,
+    tear_strain: f32,
 }
 
 @group(0) @binding(0) var<storage, read_write> vertices: array<Vertex>;
-@group(0) @binding(1) var<storage, read> springs: array<Spring>;
+@group(0) @binding(1) var<storage, read> triangles: array<u32>;
+@group(0) @binding(2) var<storage, read> vertex_triangle_offsets: array<u32>;
+@group(0) @binding(3) var<storage, read> vertex_triangles: array<u32>;
+@group(0) @binding(4) var<storage, read> springs: array<Spring>;
+@group(0) @binding(5) var<storage, read> spring_offsets: array<u32>;
 @group(1) @binding(0) var<uniform> clothe_data: ClotheData;
 
+fn is_linked(index: u32, other: u32) -> bool {
+    for (var i = spring_offsets[index]; i < spring_offsets[index + 1u]; i++) {
+        if springs[i].link == other {
+            return true;
+        }
+    }
+    return false;
+}
+
 @compute @workgroup_size(255, 1, 1) 
 fn main(@builtin(global_invocation_id) param: vec3<u32>) {
-    if (param.x > clothe_data.nb_vertices) {
+    if (param.x >= clothe_data.nb_vertices) {
         return;
     }
 
-    var vertex = vertices[param.x];
-    var spring = springs[param.x];
-
-    // Reset normals
-    vertices[param.x].normal = vec3(0.0);
-
-    // Calcul normal
-    var i: u32 = 1u;
-    var j: u32 = 0u;
-
-    // Calcul normal xwith the 8 vertices directly connected
-    while i < 8u {
-        if spring.links[j] !=  spring.links[i] {
-            var vertex_link_1 = vertices[spring.links[j]];
-            var vertex_link_2 = vertices[spring.links[i]];
-
-            vertices[param.x].normal += cross(vertex_link_1.position - vertex.position, vertex_link_2.position - vertex.position);
+    // Sum of the normals of the triangles around the vertex, weighted by
+    // their area. The torn triangles are skipped, as in `triangle_mask.wgsl`.
+    var normal = vec3(0.0);
+    for (var i = vertex_triangle_offsets[param.x]; i < vertex_triangle_offsets[param.x + 1u]; i++) {
+        let triangle = vertex_triangles[i];
+        let ia = triangles[3u * triangle];
+        let ib = triangles[3u * triangle + 1u];
+        let ic = triangles[3u * triangle + 2u];
+        if !(is_linked(ia, ib) && is_linked(ib, ic) && is_linked(ic, ia)) {
+            continue;
         }
 
-        i++;
-        j++;
+        let a = vertices[ia].position;
+        let b = vertices[ib].position;
+        let c = vertices[ic].position;
+        normal += cross(b - a, c - a);
     }
 
-    vertices[param.x].normal = -normalize(vertices[param.x].normal);
-}
\ No newline at end of file
+    if length(normal) > 0.0 {
+        vertices[param.x].normal = normalize(normal);
+    }
+}
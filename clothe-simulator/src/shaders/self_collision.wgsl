@@ -0,0 +1,143 @@
+struct Vertex {
+    position: vec3<f32>,
+    normal: vec3<f32>,
+    velocity: vec3<f32>,
+    resultant: vec3<f32>,
+    pinned: u32,
+    tex_coords: vec3<f32>,
+}
+
+// One end of a spring, see `Spring`
+struct Spring {
+    link: u32,
+    rest_distance: f32,
+    current_distance: f32,
+    lambda: f32,
+    kind: u32,
+    direction: u32,
+}
+
+struct SelfCollisionData {
+    thickness: f32,
+    table_size: u32,
+    bucket_size: u32,
+    nb_vertices: u32,
+}
+
+@group(0) @binding(0) var<storage, read_write> vertices: array<Vertex>;
+@group(0) @binding(1) var<storage, read> springs: array<Spring>;
+@group(0) @binding(2) var<storage, read_write> cell_counts: array<atomic<u32>>;
+@group(0) @binding(3) var<storage, read_write> cell_entries: array<u32>;
+@group(0) @binding(4) var<storage, read_write> corrections: array<vec4<f32>>;
+@group(0) @binding(5) var<storage, read> spring_offsets: array<u32>;
+@group(1) @binding(0) var<uniform> data: SelfCollisionData;
+
+// The grid cells are as large as the thickness, so every vertex closer than
+// the thickness lies in one of the 27 cells around the vertex
+fn cell_of(position: vec3<f32>) -> vec3<i32> {
+    return vec3<i32>(floor(position / data.thickness));
+}
+
+fn cell_hash(cell: vec3<i32>) -> u32 {
+    let c = bitcast<vec3<u32>>(cell);
+    return ((c.x * 73856093u) ^ (c.y * 19349663u) ^ (c.z * 83492791u)) % data.table_size;
+}
+
+fn is_linked(index: u32, other: u32) -> bool {
+    for (var i = spring_offsets[index]; i < spring_offsets[index + 1u]; i++) {
+        if springs[i].link == other {
+            return true;
+        }
+    }
+    return false;
+}
+
+@compute @workgroup_size(255, 1, 1)
+fn clear_cells(@builtin(global_invocation_id) param: vec3<u32>) {
+    if (param.x >= data.table_size) {
+        return;
+    }
+
+    atomicStore(&cell_counts[param.x], 0u);
+}
+
+@compute @workgroup_size(255, 1, 1)
+fn insert_vertices(@builtin(global_invocation_id) param: vec3<u32>) {
+    if (param.x >= data.nb_vertices) {
+        return;
+    }
+
+    let hash = cell_hash(cell_of(vertices[param.x].position));
+    let slot = atomicAdd(&cell_counts[hash], 1u);
+
+    // Full buckets drop the extra vertices
+    if slot < data.bucket_size {
+        cell_entries[hash * data.bucket_size + slot] = param.x;
+    }
+}
+
+@compute @workgroup_size(255, 1, 1)
+fn collide(@builtin(global_invocation_id) param: vec3<u32>) {
+    if (param.x >= data.nb_vertices) {
+        return;
+    }
+
+    corrections[param.x] = vec4(0.0);
+
+    let vertex = vertices[param.x];
+    if vertex.pinned != 0u {
+        return;
+    }
+
+    let cell = cell_of(vertex.position);
+    var visited: array<u32, 27>;
+    var nb_visited: u32 = 0u;
+    var correction = vec3(0.0);
+
+    for (var dx: i32 = -1; dx <= 1; dx++) {
+        for (var dy: i32 = -1; dy <= 1; dy++) {
+            for (var dz: i32 = -1; dz <= 1; dz++) {
+                let hash = cell_hash(cell + vec3(dx, dy, dz));
+
+                // Different cells can share the same bucket
+                var seen = false;
+                for (var v: u32 = 0u; v < nb_visited; v++) {
+                    seen = seen || visited[v] == hash;
+                }
+                if seen {
+                    continue;
+                }
+                visited[nb_visited] = hash;
+                nb_visited++;
+
+                let count = min(atomicLoad(&cell_counts[hash]), data.bucket_size);
+                for (var k: u32 = 0u; k < count; k++) {
+                    let other = cell_entries[hash * data.bucket_size + k];
+
+                    if other == param.x || is_linked(param.x, other) {
+                        continue;
+                    }
+
+                    let offset = vertex.position - vertices[other].position;
+                    let current_distance = length(offset);
+
+                    // Each vertex of the pair moves half of the overlap
+                    if current_distance < data.thickness && current_distance > 0.0 {
+                        correction += offset / current_distance * (data.thickness - current_distance) * 0.5;
+                    }
+                }
+            }
+        }
+    }
+
+    corrections[param.x] = vec4(correction, 0.0);
+}
+
+@compute @workgroup_size(255, 1, 1)
+fn apply_corrections(@builtin(global_invocation_id) param: vec3<u32>) {
+    if (param.x >= data.nb_vertices) {
+        return;
+    }
+
+    vertices[param.x].position += corrections[param.x].xyz;
+}
@@ -0,0 +1,41 @@
+// Prepended with colliders.wgsl
+
+struct ComputeData {
+    spring_contant: f32,
+    damping_factor: f32,
+    gravity: f32,
+    delta_time: f32,
+    nb_spheres: u32,
+    nb_boxes: u32,
+    nb_capsules: u32,
+    compliance: f32,
+    structural_stiffness: f32,
+    shear_stiffness: f32,
+    bend_stiffness: f32,
+    structural_damping: f32,
+    shear_damping: f32,
+    bend_damping: f32,
+    warp_stretch: f32,
+    weft_stretch: f32,
+    bias_stretch: f32,
+    warp_bend: f32,
+    weft_bend: f32,
+    bias_bend: f32,
+}
+
+@group(0) @binding(0) var<storage, read_write> spheres: array<Sphere>;
+@group(1) @binding(0) var<uniform> data: ComputeData;
+
+// The kinematic spheres move at their velocity during the substep
+@compute @workgroup_size(255, 1, 1)
+fn main(@builtin(global_invocation_id) param: vec3<u32>) {
+    if param.x >= data.nb_spheres {
+        return;
+    }
+
+    let sphere = spheres[param.x];
+    let position = vec3(sphere.x, sphere.y, sphere.z) + sphere_velocity(sphere) * data.delta_time;
+    spheres[param.x].x = position.x;
+    spheres[param.x].y = position.y;
+    spheres[param.x].z = position.z;
+}
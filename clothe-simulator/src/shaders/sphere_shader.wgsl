@@ -8,9 +8,10 @@ struct CameraUniform {
 @group(1) @binding(0)
 var<uniform> matrices: CameraUniform;
 
+// Placement of the mesh, see `MeshInstance`
 struct InstanceInput {
     @location(5) translation: vec3<f32>,
-    @location(6) velocity: vec3<f32>,
+    @location(6) scale: f32,
 };
 
 struct VertexInput {
@@ -32,7 +33,7 @@ fn vs_main(
 ) -> VertexOutput {
     var out: VertexOutput;
     out.tex_coords = model.tex_coords;
-    out.clip_position = matrices.proj * matrices.view * vec4<f32>(model.position + instance.translation, 1.0);
+    out.clip_position = matrices.proj * matrices.view * vec4<f32>(model.position * instance.scale + instance.translation, 1.0);
     return out;
 }
 
@@ -0,0 +1,49 @@
+// One end of a spring, see `Spring`
+struct Spring {
+    link: u32,
+    rest_distance: f32,
+    current_distance: f32,
+    lambda: f32,
+    kind: u32,
+    direction: u32,
+}
+
+@group(0) @binding(0) var<storage, read> springs: array<Spring>;
+@group(0) @binding(1) var<storage, read> triangles: array<u32>;
+@group(0) @binding(2) var<storage, read_write> indices: array<u32>;
+@group(0) @binding(3) var<storage, read> spring_offsets: array<u32>;
+
+fn is_linked(index: u32, other: u32) -> bool {
+    for (var i = spring_offsets[index]; i < spring_offsets[index + 1u]; i++) {
+        if springs[i].link == other {
+            return true;
+        }
+    }
+    return false;
+}
+
+// Every edge of a triangle is a spring, the triangle is torn when one of
+// them is broken. Torn triangles become degenerate and are not rasterized.
+fn visible_triangle(triangle: u32) -> vec3<u32> {
+    let a = triangles[3u * triangle];
+    let b = triangles[3u * triangle + 1u];
+    let c = triangles[3u * triangle + 2u];
+
+    if is_linked(a, b) && is_linked(b, c) && is_linked(c, a) {
+        return vec3(a, b, c);
+    }
+    return vec3(a);
+}
+
+@compute @workgroup_size(255, 1, 1)
+fn main(@builtin(global_invocation_id) param: vec3<u32>) {
+    if (param.x >= arrayLength(&triangles) / 3u) {
+        return;
+    }
+
+    let triangle = visible_triangle(param.x);
+
+    indices[3u * param.x] = triangle.x;
+    indices[3u * param.x + 1u] = triangle.y;
+    indices[3u * param.x + 2u] = triangle.z;
+}
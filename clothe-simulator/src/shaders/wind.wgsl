@@ -0,0 +1,140 @@
+struct Vertex {
+    position: vec3<f32>,
+    normal: vec3<f32>,
+    velocity: vec3<f32>,
+    resultant: vec3<f32>,
+    pinned: u32,
+    tex_coords: vec3<f32>,
+}
+
+struct ClotheData {
+    center_x: f32,
+    center_y: f32,
+    center_z: f32,
+    nb_vertices: u32,
+    tear_strain: f32,
+}
+
+struct ComputeData {
+    spring_contant: f32,
+    damping_factor: f32,
+    gravity: f32,
+    delta_time: f32,
+    nb_spheres: u32,
+    nb_boxes: u32,
+    nb_capsules: u32,
+    compliance: f32,
+    structural_stiffness: f32,
+    shear_stiffness: f32,
+    bend_stiffness: f32,
+    structural_damping: f32,
+    shear_damping: f32,
+    bend_damping: f32,
+    warp_stretch: f32,
+    weft_stretch: f32,
+    bias_stretch: f32,
+    warp_bend: f32,
+    weft_bend: f32,
+    bias_bend: f32,
+}
+
+struct WindData {
+    velocity_x: f32,
+    velocity_y: f32,
+    velocity_z: f32,
+    turbulence: f32,
+    drag_coefficient: f32,
+    lift_coefficient: f32,
+    air_density: f32,
+    time: f32,
+    nb_triangles: u32,
+}
+
+@group(0) @binding(0) var<storage, read_write> vertices: array<Vertex>;
+@group(0) @binding(1) var<storage, read> triangles: array<u32>;
+@group(0) @binding(2) var<storage, read_write> forces: array<vec4<f32>>;
+@group(0) @binding(3) var<storage, read> vertex_triangle_offsets: array<u32>;
+@group(0) @binding(4) var<storage, read> vertex_triangles: array<u32>;
+@group(0) @binding(5) var<storage, read> masses: array<f32>;
+// Storage to move the gusts forward at each substep, see `advance_time`
+@group(1) @binding(0) var<storage, read_write> wind: WindData;
+@group(1) @binding(1) var<uniform> data: ComputeData;
+@group(1) @binding(2) var<uniform> clothe_data: ClotheData;
+
+// Smooth time varying gusts, between -1 and 1 on each axis
+fn gust(p: vec3<f32>, t: f32) -> vec3<f32> {
+    return 0.5 * vec3(
+        sin(1.7 * p.y + 2.3 * t) + sin(1.1 * p.z + 1.3 * t),
+        sin(1.3 * p.z + 1.9 * t) + sin(0.9 * p.x + 2.9 * t),
+        sin(1.9 * p.x + 1.7 * t) + sin(1.5 * p.y + 2.1 * t),
+    );
+}
+
+@compute @workgroup_size(255, 1, 1)
+fn triangle_forces(@builtin(global_invocation_id) param: vec3<u32>) {
+    if (param.x >= wind.nb_triangles) {
+        return;
+    }
+
+    let v0 = vertices[triangles[3u * param.x]];
+    let v1 = vertices[triangles[3u * param.x + 1u]];
+    let v2 = vertices[triangles[3u * param.x + 2u]];
+
+    let center = (v0.position + v1.position + v2.position) / 3.0;
+    let velocity = (v0.velocity + v1.velocity + v2.velocity) / 3.0;
+    let air_velocity = vec3(wind.velocity_x, wind.velocity_y, wind.velocity_z)
+        + wind.turbulence * gust(center, wind.time);
+
+    // Air velocity seen by the triangle
+    let relative = air_velocity - velocity;
+    let speed = length(relative);
+    let area_normal = cross(v1.position - v0.position, v2.position - v0.position);
+    let area = 0.5 * length(area_normal);
+
+    forces[param.x] = vec4(0.0);
+    if speed < 1e-6 || area < 1e-12 {
+        return;
+    }
+
+    // Normal facing the air flow
+    let direction = relative / speed;
+    var normal = normalize(area_normal);
+    if dot(normal, direction) < 0.0 {
+        normal = -normal;
+    }
+    let cos_angle = dot(normal, direction);
+    let pressure = 0.5 * wind.air_density * speed * speed * area;
+
+    // Drag along the flow, lift perpendicular to it in the plane of the normal
+    // The lift direction has a length of sin(angle), the lift is the highest at 45°
+    let lift_direction = normal - cos_angle * direction;
+    let force = pressure * cos_angle
+        * (wind.drag_coefficient * direction + wind.lift_coefficient * lift_direction);
+
+    forces[param.x] = vec4(force, 0.0);
+}
+
+@compute @workgroup_size(255, 1, 1)
+fn apply_forces(@builtin(global_invocation_id) param: vec3<u32>) {
+    if (param.x >= clothe_data.nb_vertices) {
+        return;
+    }
+
+    if vertices[param.x].pinned != 0u {
+        return;
+    }
+
+    // Each triangle force is shared by its 3 vertices
+    var force = vec3(0.0);
+    for (var i = vertex_triangle_offsets[param.x]; i < vertex_triangle_offsets[param.x + 1u]; i++) {
+        force += forces[vertex_triangles[i]].xyz / 3.0;
+    }
+
+    vertices[param.x].velocity += force * data.delta_time / masses[param.x];
+}
+
+// Last stage of the substep, as the time of the CPU solver
+@compute @workgroup_size(1, 1, 1)
+fn advance_time() {
+    wind.time += data.delta_time;
+}
@@ -0,0 +1,189 @@
+// Prepended with colliders.wgsl and contacts.wgsl
+
+struct Vertex {
+    position: vec3<f32>,
+    normal: vec3<f32>,
+    velocity: vec3<f32>,
+    resultant: vec3<f32>,
+    pinned: u32,
+    tex_coords: vec3<f32>,
+}
+
+struct ClotheData {
+    center_x: f32,
+    center_y: f32,
+    center_z: f32,
+    nb_vertices: u32,
+    tear_strain: f32,
+}
+
+// One end of a spring, see `Spring`
+struct Spring {
+    link: u32,
+    rest_distance: f32,
+    current_distance: f32,
+    lambda: f32,
+    kind: u32,
+    direction: u32,
+}
+
+struct ComputeData {
+    spring_contant: f32,
+    damping_factor: f32,
+    gravity: f32,
+    delta_time: f32,
+    nb_spheres: u32,
+    nb_boxes: u32,
+    nb_capsules: u32,
+    compliance: f32,
+    structural_stiffness: f32,
+    shear_stiffness: f32,
+    bend_stiffness: f32,
+    structural_damping: f32,
+    shear_damping: f32,
+    bend_damping: f32,
+    warp_stretch: f32,
+    weft_stretch: f32,
+    bias_stretch: f32,
+    warp_bend: f32,
+    weft_bend: f32,
+    bias_bend: f32,
+}
+
+// Memory of the solver, see `XpbdState`
+struct XpbdState {
+    previous_position: vec3<f32>,
+    correction: vec3<f32>,
+}
+
+@group(0) @binding(0) var<storage, read_write> vertices: array<Vertex>;
+@group(0) @binding(1) var<storage, read_write> springs: array<Spring>;
+@group(0) @binding(2) var<storage, read_write> states: array<XpbdState>;
+@group(0) @binding(3) var<storage, read> spring_offsets: array<u32>;
+@group(0) @binding(4) var<storage, read> masses: array<f32>;
+@group(1) @binding(0) var<storage, read> spheres: array<Sphere>;
+@group(1) @binding(1) var<uniform> data: ComputeData;
+@group(1) @binding(2) var<uniform> clothe_data: ClotheData;
+@group(1) @binding(3) var<uniform> plane: Plane;
+@group(1) @binding(4) var<storage, read> boxes: array<OrientedBox>;
+@group(1) @binding(5) var<storage, read> capsules: array<Capsule>;
+
+// The corrections of a vertex are averaged over its constraints, then
+// over-relaxed so the Jacobi iterations converge faster
+const RELAXATION: f32 = 1.5;
+
+// Index of `SpringKind::Bend`
+const BEND: u32 = 2u;
+
+// Factor of the stiffness for the kind and the direction of a spring, see
+// `SpringKind` and `SpringDirection`
+fn spring_stiffness(spring: Spring) -> f32 {
+    let kind = vec3(data.structural_stiffness, data.shear_stiffness, data.bend_stiffness)[spring.kind];
+    var direction = vec3(data.warp_stretch, data.weft_stretch, data.bias_stretch);
+    if spring.kind == BEND {
+        direction = vec3(data.warp_bend, data.weft_bend, data.bias_bend);
+    }
+
+    return kind * direction[spring.direction];
+}
+
+fn inverse_mass(index: u32) -> f32 {
+    return select(1.0 / masses[index], 0.0, vertices[index].pinned != 0u);
+}
+
+// Move the vertices with the external forces only, the springs are solved
+// afterwards as distance constraints
+@compute @workgroup_size(255, 1, 1)
+fn predict(@builtin(global_invocation_id) param: vec3<u32>) {
+    if (param.x >= clothe_data.nb_vertices) {
+        return;
+    }
+
+    let vertex = vertices[param.x];
+    states[param.x].previous_position = vertex.position;
+    for (var i = spring_offsets[param.x]; i < spring_offsets[param.x + 1u]; i++) {
+        springs[i].lambda = 0.0;
+    }
+
+    // Pinned vertices are not integrated
+    if vertex.pinned != 0u {
+        vertices[param.x].velocity = vec3(0.0);
+        vertices[param.x].resultant = vec3(0.0);
+        return;
+    }
+
+    let mass = masses[param.x];
+    var resultant = vec3(0.0, data.gravity * mass, 0.0) - vertex.velocity * data.damping_factor;
+    resultant = contact_friction(resultant, vertex.position);
+
+    let velocity = vertex.velocity + resultant * data.delta_time / masses[param.x];
+    vertices[param.x].velocity = velocity;
+    vertices[param.x].position = vertex.position + velocity * data.delta_time;
+    vertices[param.x].resultant = resultant;
+}
+
+// One Jacobi iteration: every vertex solves the constraints of its links
+// from the same positions, both ends of a link find the same lambda
+@compute @workgroup_size(255, 1, 1)
+fn solve_constraints(@builtin(global_invocation_id) param: vec3<u32>) {
+    if (param.x >= clothe_data.nb_vertices) {
+        return;
+    }
+
+    let position = vertices[param.x].position;
+    let inverse_mass_vertex = inverse_mass(param.x);
+
+    var correction = vec3(0.0);
+    var nb_constraints = 0.0;
+
+    for (var i = spring_offsets[param.x]; i < spring_offsets[param.x + 1u]; i++) {
+        let spring = springs[i];
+        let link = spring.link;
+        if link == param.x {
+            continue;
+        }
+
+        let offset = position - vertices[link].position;
+        let current_distance = length(offset);
+        let inverse_masses = inverse_mass_vertex + inverse_mass(link);
+        let stiffness = spring_stiffness(spring);
+        if current_distance < 1e-9 || inverse_masses == 0.0 || stiffness <= 0.0 {
+            continue;
+        }
+
+        // Softer kinds of springs are more compliant constraints
+        let alpha = data.compliance / (stiffness * data.delta_time * data.delta_time);
+        let constraint = current_distance - spring.rest_distance;
+        let delta_lambda = (-constraint - alpha * spring.lambda) / (inverse_masses + alpha);
+        springs[i].lambda = spring.lambda + delta_lambda;
+
+        correction += inverse_mass_vertex * delta_lambda * offset / current_distance;
+        nb_constraints += 1.0;
+    }
+
+    states[param.x].correction = correction * RELAXATION / max(nb_constraints, 1.0);
+}
+
+@compute @workgroup_size(255, 1, 1)
+fn apply_corrections(@builtin(global_invocation_id) param: vec3<u32>) {
+    if (param.x >= clothe_data.nb_vertices) {
+        return;
+    }
+
+    vertices[param.x].position += states[param.x].correction;
+}
+
+// Velocities from the solved positions, then the colliders
+@compute @workgroup_size(255, 1, 1)
+fn finalize(@builtin(global_invocation_id) param: vec3<u32>) {
+    if (param.x >= clothe_data.nb_vertices || vertices[param.x].pinned != 0u) {
+        return;
+    }
+
+    let position = vertices[param.x].position;
+    let velocity = (position - states[param.x].previous_position) / data.delta_time;
+    let state = collide(State(position, velocity));
+
+    vertices[param.x].position = state.position;
+    vertices[param.x].velocity = state.velocity;
+}
@@ -1,17 +1,129 @@
+use std::ops::Range;
+
+// One end of a spring. The springs of the vertex `i` are
+// `springs[offsets[i]..offsets[i + 1]]`, each spring is stored at both ends.
+// A broken spring links the vertex to itself.
 #[repr(C)]
 #[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Spring {
-    pub links: [u32; 12],
-    pub rest_distance: [f32; 12],
-    pub current_distance: [f32; 12],
+    pub link: u32,
+    pub rest_distance: f32,
+    pub current_distance: f32,
+    pub lambda: f32, // Lagrange multiplier of the XPBD solver, reset at each substep
+    pub kind: u32,
+    pub direction: u32,
 }
 
 impl Spring {
-    pub fn new() -> Self {
+    pub fn new(link: u32, rest_distance: f32, kind: SpringKind, direction: SpringDirection) -> Self {
         Self {
-            links: Default::default(),
-            rest_distance: Default::default(),
-            current_distance: Default::default(),
+            link,
+            rest_distance,
+            current_distance: rest_distance,
+            lambda: 0.0,
+            kind: kind.index(),
+            direction: direction.index(),
         }
     }
-}
\ No newline at end of file
+}
+
+// Springs of the vertex `index` in the flat spring list
+pub fn spring_range(offsets: &[u32], index: usize) -> Range<usize> {
+    offsets[index] as usize..offsets[index + 1] as usize
+}
+
+// Role of a spring in the clothe, each kind has its own stiffness and damping
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SpringKind {
+    Structural,
+    Shear,
+    Bend,
+}
+
+impl SpringKind {
+    pub fn index(self) -> u32 {
+        self as u32
+    }
+
+    pub fn from_index(index: u32) -> Self {
+        match index {
+            1 => SpringKind::Shear,
+            2 => SpringKind::Bend,
+            _ => SpringKind::Structural,
+        }
+    }
+}
+
+// Direction of a spring in the fabric, given by the texture coordinates: the
+// weft follows the u axis, the warp the v axis and the bias is in between
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpringDirection {
+    Warp,
+    Weft,
+    Bias,
+}
+
+impl SpringDirection {
+    pub fn index(self) -> u32 {
+        self as u32
+    }
+
+    pub fn from_index(index: u32) -> Self {
+        match index {
+            1 => SpringDirection::Weft,
+            2 => SpringDirection::Bias,
+            _ => SpringDirection::Warp,
+        }
+    }
+
+    // Within 22.5 degrees of an axis, the spring follows this axis. Vertices
+    // with the same texture coordinates are on the weft.
+    pub fn from_tex_coords(a: [f32; 2], b: [f32; 2]) -> Self {
+        let du = (b[0] - a[0]).abs();
+        let dv = (b[1] - a[1]).abs();
+        let tan = std::f32::consts::FRAC_PI_8.tan();
+
+        if dv <= du * tan {
+            SpringDirection::Weft
+        } else if du <= dv * tan {
+            SpringDirection::Warp
+        } else {
+            SpringDirection::Bias
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Texture coordinates at `degrees` from the u axis
+    fn at_angle(degrees: f32) -> [f32; 2] {
+        let radians = degrees.to_radians();
+        [0.1 + radians.cos(), 0.2 + radians.sin()]
+    }
+
+    #[test]
+    fn springs_follow_the_closest_axis() {
+        for (degrees, direction) in [
+            (0.0, SpringDirection::Weft),
+            (180.0, SpringDirection::Weft),
+            (22.0, SpringDirection::Weft),
+            (23.0, SpringDirection::Bias),
+            (45.0, SpringDirection::Bias),
+            (-45.0, SpringDirection::Bias),
+            (67.0, SpringDirection::Bias),
+            (68.0, SpringDirection::Warp),
+            (90.0, SpringDirection::Warp),
+            (-90.0, SpringDirection::Warp),
+        ] {
+            assert_eq!(SpringDirection::from_tex_coords([0.1, 0.2], at_angle(degrees)), direction, "{}°", degrees);
+        }
+    }
+
+    #[test]
+    fn same_tex_coords_are_on_the_weft() {
+        assert_eq!(SpringDirection::from_tex_coords([0.5, 0.5], [0.5, 0.5]), SpringDirection::Weft);
+        assert_eq!(SpringDirection::from_tex_coords([0.0, 0.0], [0.0, 0.0]), SpringDirection::Weft);
+    }
+}
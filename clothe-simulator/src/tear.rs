@@ -0,0 +1,40 @@
+use wgpu_bootstrap::wgpu;
+
+use crate::gpu::{create_buffer_bind_group, create_compute_pipeline, get_workers, GpuContext};
+
+/// GPU pass hiding the torn triangles: the render index buffer is rebuilt from
+/// the original triangles and the ones with a broken edge become degenerate.
+/// The index buffer needs the `STORAGE` usage.
+pub struct TriangleMask {
+    pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+    workers: u32,
+}
+
+impl TriangleMask {
+    pub fn new(context: &impl GpuContext, spring_buffer: &wgpu::Buffer, spring_offset_buffer: &wgpu::Buffer,
+        index_buffer: &wgpu::Buffer, indices: &[u32]) -> Self {
+        let triangle_buffer = context.create_buffer(indices, wgpu::BufferUsages::STORAGE);
+
+        let pipeline = create_compute_pipeline(context, "Triangle Mask",
+            include_str!("shaders/triangle_mask.wgsl"), "main");
+        let bind_group = create_buffer_bind_group(
+            context,
+            "Triangle Mask",
+            &pipeline.get_bind_group_layout(0),
+            &[(0, spring_buffer), (1, &triangle_buffer), (2, index_buffer), (3, spring_offset_buffer)],
+        );
+
+        Self {
+            pipeline,
+            bind_group,
+            workers: get_workers((indices.len() / 3) as u32),
+        }
+    }
+
+    pub fn dispatch<'a>(&'a self, compute_pass: &mut wgpu::ComputePass<'a>) {
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &self.bind_group, &[]);
+        compute_pass.dispatch_workgroups(self.workers, 1, 1);
+    }
+}
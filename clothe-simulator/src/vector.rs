@@ -0,0 +1,56 @@
+// Small vector helpers for the CPU side computations
+
+pub fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+pub fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub fn scale(a: [f32; 3], s: f32) -> [f32; 3] {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+pub fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+pub fn length(a: [f32; 3]) -> f32 {
+    dot(a, a).sqrt()
+}
+
+pub fn distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    length(sub(a, b))
+}
+
+// Null vectors are returned unchanged
+pub fn normalize(a: [f32; 3]) -> [f32; 3] {
+    let norm = length(a);
+
+    if norm > 0.0 {
+        scale(a, 1.0 / norm)
+    } else {
+        a
+    }
+}
+
+// Rotate `v` by the unit quaternion `q` = [x, y, z, w]
+pub fn quat_rotate(q: [f32; 4], v: [f32; 3]) -> [f32; 3] {
+    let axis = [q[0], q[1], q[2]];
+    let t = scale(cross(axis, v), 2.0);
+
+    add(add(v, scale(t, q[3])), cross(axis, t))
+}
+
+pub fn quat_conjugate(q: [f32; 4]) -> [f32; 4] {
+    [-q[0], -q[1], -q[2], q[3]]
+}
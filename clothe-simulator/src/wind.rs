@@ -0,0 +1,65 @@
+use wgpu_bootstrap::wgpu;
+
+use crate::{
+    data_containers::WindData,
+    gpu::{create_storage_buffer, ComputeStage, GpuContext},
+    mesh::TriangleBuffers,
+};
+
+/// GPU wind stage: the aerodynamic force of every triangle is computed, then
+/// each vertex gathers the forces of its triangles into its velocity. The
+/// gusts move forward by one substep at the end.
+pub struct Wind {
+    data: WindData,
+    data_buffer: wgpu::Buffer,
+    stages: [ComputeStage; 3],
+}
+
+impl Wind {
+    pub fn new(
+        context: &impl GpuContext,
+        vertex_buffer: &wgpu::Buffer,
+        compute_data_buffer: &wgpu::Buffer,
+        clothe_data_buffer: &wgpu::Buffer,
+        triangles: &TriangleBuffers,
+        mass_buffer: &wgpu::Buffer,
+        data: WindData,
+    ) -> Self {
+        let source = include_str!("shaders/wind.wgsl");
+
+        let data_buffer = create_storage_buffer(context, &[data], wgpu::BufferUsages::empty());
+        let force_buffer = create_storage_buffer(context, &vec![[0.0f32; 4]; triangles.nb_triangles as usize],
+            wgpu::BufferUsages::empty());
+
+        let stages = [
+            ComputeStage::new(context, "Wind", source, "triangle_forces",
+                [&[(0, vertex_buffer), (1, &triangles.triangles), (2, &force_buffer)], &[(0, &data_buffer)]],
+                data.nb_triangles),
+            ComputeStage::new(context, "Wind", source, "apply_forces",
+                [&[(0, vertex_buffer), (2, &force_buffer), (3, &triangles.offsets), (4, &triangles.adjacent),
+                    (5, mass_buffer)],
+                    &[(1, compute_data_buffer), (2, clothe_data_buffer)]],
+                triangles.nb_vertices),
+            ComputeStage::new(context, "Wind", source, "advance_time",
+                [&[], &[(0, &data_buffer), (1, compute_data_buffer)]], 1),
+        ];
+
+        Self {
+            data,
+            data_buffer,
+            stages,
+        }
+    }
+
+    /// Time of the gusts at the start of the next substep, in seconds.
+    pub fn set_time(&mut self, context: &impl GpuContext, time: f32) {
+        self.data.time = time;
+        context.update_buffer(&self.data_buffer, &[self.data]);
+    }
+
+    pub fn dispatch<'a>(&'a self, compute_pass: &mut wgpu::ComputePass<'a>) {
+        for stage in &self.stages {
+            stage.dispatch(compute_pass);
+        }
+    }
+}
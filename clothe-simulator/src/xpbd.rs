@@ -0,0 +1,31 @@
+use crate::node::Node;
+
+// Per vertex memory of the XPBD solver: the position at the beginning of the
+// substep and the last Jacobi correction. The Lagrange multipliers are kept
+// in the springs.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct XpbdState {
+    pub previous_position: [f32; 4],
+    pub correction: [f32; 4],
+}
+
+impl XpbdState {
+    pub fn new(vertex: &Node) -> Self {
+        Self {
+            previous_position: vertex.position,
+            correction: [0.0; 4],
+        }
+    }
+}
+
+/// Entry points of `xpbd.wgsl` dispatched in order for one substep.
+pub fn entry_points(constraint_iterations: u32) -> Vec<&'static str> {
+    let mut entry_points = vec!["predict"];
+    for _ in 0..constraint_iterations {
+        entry_points.extend(["solve_constraints", "apply_corrections"]);
+    }
+    entry_points.push("finalize");
+
+    entry_points
+}